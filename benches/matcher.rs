@@ -0,0 +1,149 @@
+//! Compares the compiled multi-pattern matcher with checking each pattern
+//! in turn, and encoding every key with ruling keys out from their raw
+//! bytes first. Run with `cargo bench --bench matcher`.
+
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+};
+use std::time::{Duration, Instant};
+use vanaddy::{
+    base58::ALPHABET,
+    matcher::{MatchPosition, Matcher, PatternSet},
+};
+
+const ADDRESS_COUNT: usize = 20_000;
+const PATTERN_COUNTS: [usize; 4] = [1, 10, 100, 1000];
+
+fn main() {
+    let keys: Vec<Pubkey> = (0..ADDRESS_COUNT)
+        .map(|_| Keypair::new().pubkey())
+        .collect();
+    let addresses: Vec<String> = keys.iter().map(Pubkey::to_string).collect();
+    let mut seed = 0x5eed_u64;
+
+    compare_matchers(&addresses, &mut seed);
+    println!();
+    compare_prefilter(&keys, &mut seed);
+}
+
+fn compare_matchers(addresses: &[String], seed: &mut u64) {
+    println!(
+        "{:<9} {:>8} {:>14} {:>14} {:>8}",
+        "position", "patterns", "naive", "automaton", "speedup"
+    );
+    for position in [
+        MatchPosition::Prefix,
+        MatchPosition::Suffix,
+        MatchPosition::Both,
+        MatchPosition::Anywhere,
+    ] {
+        for count in PATTERN_COUNTS {
+            let patterns: Vec<String> = (0..count).map(|_| random_word(seed)).collect();
+            let matchers: Vec<Matcher> = patterns
+                .iter()
+                .map(|pattern| Matcher::new(pattern, position, false))
+                .collect();
+            let set = PatternSet::new(&patterns, position, false);
+
+            let (naive_time, naive_hits) = time(|| {
+                addresses
+                    .iter()
+                    .map(|address| {
+                        matchers
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, matcher)| matcher.is_match(address))
+                            .map(|(index, _)| index)
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<Vec<_>>()
+            });
+            let (automaton_time, automaton_hits) = time(|| {
+                addresses
+                    .iter()
+                    .map(|address| set.matches(address))
+                    .collect::<Vec<_>>()
+            });
+            assert_eq!(naive_hits, automaton_hits, "matchers disagree");
+
+            println!(
+                "{:<9} {:>8} {:>11.1} ns {:>11.1} ns {:>7.1}x",
+                position,
+                count,
+                per_address(naive_time),
+                per_address(automaton_time),
+                naive_time.as_secs_f64() / automaton_time.as_secs_f64()
+            );
+        }
+    }
+}
+
+fn compare_prefilter(keys: &[Pubkey], seed: &mut u64) {
+    println!(
+        "{:<9} {:>8} {:>14} {:>14} {:>8}",
+        "position", "patterns", "encode all", "prefilter", "speedup"
+    );
+    for position in [
+        MatchPosition::Prefix,
+        MatchPosition::Suffix,
+        MatchPosition::Both,
+    ] {
+        for count in PATTERN_COUNTS {
+            let patterns: Vec<String> = (0..count).map(|_| random_word(seed)).collect();
+            let set = PatternSet::new(&patterns, position, false);
+
+            let (encode_time, encode_hits) = time(|| {
+                keys.iter()
+                    .map(|key| set.matches(&key.to_string()))
+                    .collect::<Vec<_>>()
+            });
+            let (filter_time, filter_hits) = time(|| {
+                keys.iter()
+                    .map(|key| {
+                        if set.may_match(&key.to_bytes()) {
+                            set.matches(&key.to_string())
+                        } else {
+                            Vec::new()
+                        }
+                    })
+                    .collect::<Vec<_>>()
+            });
+            assert_eq!(encode_hits, filter_hits, "prefilter rejected a match");
+
+            println!(
+                "{:<9} {:>8} {:>11.1} ns {:>11.1} ns {:>7.1}x",
+                position,
+                count,
+                per_address(encode_time),
+                per_address(filter_time),
+                encode_time.as_secs_f64() / filter_time.as_secs_f64()
+            );
+        }
+    }
+}
+
+fn time<T>(f: impl FnOnce() -> T) -> (Duration, T) {
+    let start = Instant::now();
+    let result = f();
+    (start.elapsed(), result)
+}
+
+fn per_address(duration: Duration) -> f64 {
+    duration.as_nanos() as f64 / ADDRESS_COUNT as f64
+}
+
+/// A 2-5 character base58 word from a simple xorshift generator, so runs
+/// are repeatable.
+fn random_word(seed: &mut u64) -> String {
+    let mut next = || {
+        *seed ^= *seed << 13;
+        *seed ^= *seed >> 7;
+        *seed ^= *seed << 17;
+        *seed
+    };
+    let len = 2 + (next() % 4) as usize;
+    (0..len)
+        .map(|_| ALPHABET[(next() % ALPHABET.len() as u64) as usize] as char)
+        .collect()
+}
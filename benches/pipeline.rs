@@ -0,0 +1,107 @@
+//! Compares the per-key work of the old worker loop, which encoded every
+//! public and secret key into a batch before filtering, with `check_keypair`,
+//! which leaves the secret inside the `Keypair` until a match is confirmed.
+//! Key generation is done up front so only the pipeline itself is timed.
+//! Run with `cargo bench --bench pipeline`.
+
+use solana_sdk::{
+    bs58,
+    signature::{Keypair, Signer},
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+use vanaddy::{
+    matcher::{MatchPosition, PatternSet},
+    search::{self, FoundKey},
+};
+
+const KEY_COUNT: usize = 20_000;
+
+/// Counts every allocation made through the global allocator.
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn main() {
+    let keypairs: Vec<Keypair> = (0..KEY_COUNT).map(|_| Keypair::new()).collect();
+
+    println!(
+        "{:<9} {:>14} {:>14} {:>14} {:>14}",
+        "position", "encode all", "allocs/key", "raw secret", "allocs/key"
+    );
+    for position in [
+        MatchPosition::Prefix,
+        MatchPosition::Suffix,
+        MatchPosition::Anywhere,
+    ] {
+        let patterns = PatternSet::new(&["pump", "bonk", "moon"], position, false);
+
+        let (old_ns, old_allocs, old_found) = measure(|| encode_all(&keypairs, &patterns));
+        let (new_ns, new_allocs, new_found) = measure(|| raw_secret(&keypairs, &patterns));
+        assert_eq!(old_found, new_found, "pipelines disagree");
+
+        println!(
+            "{:<9} {:>11.1} ns {:>14.2} {:>11.1} ns {:>14.2}",
+            position, old_ns, old_allocs, new_ns, new_allocs
+        );
+    }
+}
+
+/// The worker loop before secrets were kept raw.
+fn encode_all(keypairs: &[Keypair], patterns: &PatternSet) -> Vec<String> {
+    let batch: Vec<(String, String)> = keypairs
+        .iter()
+        .map(|keypair| {
+            let public_key = keypair.pubkey().to_string();
+            let private_key = bs58::encode(keypair.to_bytes()).into_string();
+            (public_key, private_key)
+        })
+        .collect();
+    batch
+        .into_iter()
+        .filter(|(public_key, _)| !patterns.matches(public_key).is_empty())
+        .map(|(public_key, _)| public_key)
+        .collect()
+}
+
+fn raw_secret(keypairs: &[Keypair], patterns: &PatternSet) -> Vec<String> {
+    keypairs
+        .iter()
+        .filter_map(|keypair| {
+            let (public_key, hits) =
+                search::check_public_key(&keypair.pubkey().to_bytes(), patterns)?;
+            Some(FoundKey::new(keypair, public_key, patterns.pattern(hits[0]), 0, 0).public_key)
+        })
+        .collect()
+}
+
+/// Runs `f` once, returning nanoseconds and allocations per key.
+fn measure<T>(f: impl FnOnce() -> T) -> (f64, f64, T) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    (
+        elapsed.as_nanos() as f64 / KEY_COUNT as f64,
+        allocations as f64 / KEY_COUNT as f64,
+        result,
+    )
+}
@@ -0,0 +1,308 @@
+use crate::{base58::ALPHABET, matcher::MatchPosition};
+use std::collections::VecDeque;
+
+/// Marks a missing transition in an anchored trie.
+const NO_STATE: u32 = u32::MAX;
+
+/// Marks a byte that never appears in an address.
+const NO_SYMBOL: u8 = u8::MAX;
+
+const ROOT: usize = 0;
+
+/// Matches addresses against any number of vanity strings at once.
+///
+/// Patterns are compiled into tries over the 58-symbol base58 alphabet:
+/// prefixes are found by walking a trie from the start of the address,
+/// suffixes by walking a trie of reversed patterns from the end, and
+/// substrings by running an Aho-Corasick automaton over the whole address.
+/// Each address is read at most once per anchor, so the cost of a miss does
+/// not grow with the number of patterns.
+#[derive(Debug, Clone)]
+pub struct PatternAutomaton {
+    position: MatchPosition,
+    symbols: [u8; 256],
+    /// Patterns read forwards; a full automaton for `anywhere`.
+    forward: Trie,
+    /// Patterns read backwards, for suffix anchors.
+    backward: Trie,
+}
+
+impl PatternAutomaton {
+    pub fn new<S: AsRef<str>>(
+        patterns: &[S],
+        position: MatchPosition,
+        case_sensitive: bool,
+    ) -> Self {
+        let symbols = symbol_table(case_sensitive);
+        let encode = |pattern: &str| -> Option<Vec<u8>> {
+            pattern
+                .bytes()
+                .map(|b| Some(symbols[b as usize]).filter(|&s| s != NO_SYMBOL))
+                .collect()
+        };
+
+        let mut forward = Trie::new();
+        let mut backward = Trie::new();
+        let anchored_at_start = matches!(
+            position,
+            MatchPosition::Prefix | MatchPosition::Both | MatchPosition::Anywhere
+        );
+        let anchored_at_end = matches!(position, MatchPosition::Suffix | MatchPosition::Both);
+        for (index, pattern) in patterns.iter().enumerate() {
+            // Patterns with characters outside the alphabet can never match.
+            let Some(encoded) = encode(pattern.as_ref()) else {
+                continue;
+            };
+            if anchored_at_start {
+                forward.insert(encoded.iter().copied(), index);
+            }
+            if anchored_at_end {
+                backward.insert(encoded.iter().rev().copied(), index);
+            }
+        }
+        if position == MatchPosition::Anywhere {
+            forward.build_links();
+        }
+
+        PatternAutomaton {
+            position,
+            symbols,
+            forward,
+            backward,
+        }
+    }
+
+    /// Indexes of every pattern `address` matches, in ascending order.
+    pub fn matches(&self, address: &str) -> Vec<usize> {
+        let address = address.as_bytes();
+        match self.position {
+            MatchPosition::Prefix => self.walk(&self.forward, address.iter()),
+            MatchPosition::Suffix => self.walk(&self.backward, address.iter().rev()),
+            MatchPosition::Both => {
+                let at_start = self.walk(&self.forward, address.iter());
+                if at_start.is_empty() {
+                    return at_start;
+                }
+                let at_end = self.walk(&self.backward, address.iter().rev());
+                at_start
+                    .into_iter()
+                    .filter(|index| at_end.binary_search(index).is_ok())
+                    .collect()
+            }
+            MatchPosition::Anywhere => self.scan(address),
+        }
+    }
+
+    /// Follows an anchored trie until it runs out of transitions.
+    fn walk<'a>(&self, trie: &Trie, bytes: impl Iterator<Item = &'a u8>) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut state = ROOT;
+        for &b in bytes {
+            let symbol = self.symbols[b as usize];
+            if symbol == NO_SYMBOL {
+                break;
+            }
+            match trie.transitions[state][symbol as usize] {
+                NO_STATE => break,
+                next => state = next as usize,
+            }
+            found.extend_from_slice(&trie.outputs[state]);
+        }
+        found.sort_unstable();
+        found
+    }
+
+    /// Runs the Aho-Corasick automaton over the whole address.
+    fn scan(&self, address: &[u8]) -> Vec<usize> {
+        let mut found = Vec::new();
+        let mut state = ROOT;
+        for &b in address {
+            let symbol = self.symbols[b as usize];
+            if symbol == NO_SYMBOL {
+                state = ROOT;
+                continue;
+            }
+            state = self.forward.transitions[state][symbol as usize] as usize;
+            found.extend_from_slice(&self.forward.outputs[state]);
+        }
+        found.sort_unstable();
+        found.dedup();
+        found
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Trie {
+    transitions: Vec<[u32; ALPHABET.len()]>,
+    /// Patterns that end at each state.
+    outputs: Vec<Vec<usize>>,
+}
+
+impl Trie {
+    fn new() -> Self {
+        Trie {
+            transitions: vec![[NO_STATE; ALPHABET.len()]],
+            outputs: vec![Vec::new()],
+        }
+    }
+
+    fn insert(&mut self, symbols: impl Iterator<Item = u8>, index: usize) {
+        let mut state = ROOT;
+        for symbol in symbols {
+            state = match self.transitions[state][symbol as usize] {
+                NO_STATE => {
+                    let next = self.transitions.len();
+                    self.transitions.push([NO_STATE; ALPHABET.len()]);
+                    self.outputs.push(Vec::new());
+                    self.transitions[state][symbol as usize] = next as u32;
+                    next
+                }
+                next => next as usize,
+            };
+        }
+        self.outputs[state].push(index);
+    }
+
+    /// Turns the trie into a complete automaton by filling every missing
+    /// transition from the longest proper suffix that is also in the trie.
+    fn build_links(&mut self) {
+        let mut fail = vec![ROOT; self.transitions.len()];
+        let mut queue = VecDeque::new();
+        for symbol in 0..ALPHABET.len() {
+            match self.transitions[ROOT][symbol] {
+                NO_STATE => self.transitions[ROOT][symbol] = ROOT as u32,
+                child => queue.push_back(child as usize),
+            }
+        }
+        while let Some(state) = queue.pop_front() {
+            let inherited = self.outputs[fail[state]].clone();
+            self.outputs[state].extend(inherited);
+            for symbol in 0..ALPHABET.len() {
+                let fallback = self.transitions[fail[state]][symbol];
+                match self.transitions[state][symbol] {
+                    NO_STATE => self.transitions[state][symbol] = fallback,
+                    child => {
+                        fail[child as usize] = fallback as usize;
+                        queue.push_back(child as usize);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Maps address bytes to symbols, giving both cases of a letter the same
+/// symbol when matching is case-insensitive.
+fn symbol_table(case_sensitive: bool) -> [u8; 256] {
+    let mut table = [NO_SYMBOL; 256];
+    for (symbol, &c) in ALPHABET.iter().enumerate() {
+        table[c as usize] = symbol as u8;
+    }
+    if !case_sensitive {
+        // Prefer the lowercase spelling so `A`/`a` share a symbol, falling
+        // back to uppercase for `L`, whose lowercase is not in the alphabet.
+        for c in b'a'..=b'z' {
+            let upper = c.to_ascii_uppercase();
+            let symbol = match table[c as usize] {
+                NO_SYMBOL => table[upper as usize],
+                symbol => symbol,
+            };
+            table[c as usize] = symbol;
+            table[upper as usize] = symbol;
+        }
+    }
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::Matcher;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    const POSITIONS: [MatchPosition; 4] = [
+        MatchPosition::Prefix,
+        MatchPosition::Suffix,
+        MatchPosition::Both,
+        MatchPosition::Anywhere,
+    ];
+
+    fn naive(
+        patterns: &[&str],
+        position: MatchPosition,
+        case_sensitive: bool,
+        address: &str,
+    ) -> Vec<usize> {
+        (0..patterns.len())
+            .filter(|&i| Matcher::new(patterns[i], position, case_sensitive).is_match(address))
+            .collect()
+    }
+
+    #[test]
+    fn overlapping_patterns_are_all_found() {
+        let patterns = ["ab", "b", "bab", "abab", "c"];
+        let automaton = PatternAutomaton::new(&patterns, MatchPosition::Anywhere, true);
+        // `bab` is only reached through the failure link from `ab`'s `b`.
+        assert_eq!(automaton.matches("xabab"), vec![0, 1, 2, 3]);
+        assert_eq!(automaton.matches("xbax"), vec![1]);
+        assert_eq!(automaton.matches("aab"), vec![0, 1]);
+        assert_eq!(automaton.matches("xyz"), Vec::<usize>::new());
+
+        let automaton = PatternAutomaton::new(&patterns, MatchPosition::Both, true);
+        assert_eq!(automaton.matches("babxbab"), vec![1, 2]);
+        assert_eq!(automaton.matches("abab"), vec![0, 3]);
+    }
+
+    #[test]
+    fn case_folding_follows_the_alphabet() {
+        // `l` and `I` only exist as `L` and `i` in addresses, `O` only as `o`.
+        let patterns = ["l", "O", "I", "Ab"];
+        let automaton = PatternAutomaton::new(&patterns, MatchPosition::Anywhere, false);
+        assert_eq!(automaton.matches("xLx"), vec![0]);
+        assert_eq!(automaton.matches("xoix"), vec![1, 2]);
+        assert_eq!(automaton.matches("aB"), vec![3]);
+        let automaton = PatternAutomaton::new(&patterns, MatchPosition::Anywhere, true);
+        assert_eq!(automaton.matches("xLoiAB"), Vec::<usize>::new());
+        assert_eq!(automaton.matches("Ab"), vec![3]);
+    }
+
+    #[test]
+    fn agrees_with_one_matcher_per_pattern() {
+        let mut rng = StdRng::seed_from_u64(7);
+        // Few characters, so patterns overlap and hit often; the patterns
+        // also use case spellings that base58 lacks.
+        let address_chars = b"abAB1Lio";
+        let pattern_chars = b"abAB1LlIiOo";
+        let string = |rng: &mut StdRng, chars: &[u8], len: usize| -> String {
+            (0..len)
+                .map(|_| *chars.choose(rng).unwrap() as char)
+                .collect()
+        };
+        for round in 0..200 {
+            let patterns: Vec<String> = (0..rng.gen_range(1..6))
+                .map(|_| {
+                    let len = rng.gen_range(1..4);
+                    string(&mut rng, pattern_chars, len)
+                })
+                .collect();
+            let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+            let case_sensitive = round % 2 == 0;
+            for position in POSITIONS {
+                let automaton = PatternAutomaton::new(&patterns, position, case_sensitive);
+                for _ in 0..50 {
+                    let len = rng.gen_range(1..45);
+                    let address = string(&mut rng, address_chars, len);
+                    assert_eq!(
+                        automaton.matches(&address),
+                        naive(&patterns, position, case_sensitive, &address),
+                        "{:?} {:?} case_sensitive={} in {}",
+                        patterns,
+                        position,
+                        case_sensitive,
+                        address
+                    );
+                }
+            }
+        }
+    }
+}
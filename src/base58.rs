@@ -0,0 +1,221 @@
+use crate::matcher::MatchPosition;
+use num_bigint::BigUint;
+
+/// The Bitcoin base58 alphabet used for Solana addresses.
+pub const ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Length in bytes of an ed25519 public key.
+pub const PUBKEY_BYTES: usize = 32;
+
+/// Position of `c` in [`ALPHABET`], i.e. its value as a base58 digit.
+pub fn digit(c: u8) -> Option<u8> {
+    ALPHABET.iter().position(|&a| a == c).map(|i| i as u8)
+}
+
+/// Checks that a vanity pattern can actually occur in a Solana address.
+///
+/// Every character has to be in the base58 alphabet (or, for case-insensitive
+/// searches, have a case variant that is), and a pattern anchored at the
+/// start of the address has to be reachable by some 32-byte key.
+pub fn validate_pattern(
+    pattern: &str,
+    position: MatchPosition,
+    case_sensitive: bool,
+) -> Result<(), String> {
+    for (index, c) in pattern.chars().enumerate() {
+        if case_variants(c, case_sensitive).is_empty() {
+            return Err(invalid_char_message(c, index + 1));
+        }
+    }
+
+    let anchored_at_start = matches!(position, MatchPosition::Prefix | MatchPosition::Both);
+    if anchored_at_start
+        && !prefix_variants(pattern, case_sensitive)
+            .iter()
+            .any(|prefix| !prefix_ranges(prefix).is_empty())
+    {
+        return Err(format!(
+            "no 32-byte public key has an address starting with `{}`",
+            pattern
+        ));
+    }
+    Ok(())
+}
+
+/// Every spelling of `pattern` that the search will accept, restricted to
+/// the base58 alphabet.
+pub fn prefix_variants(pattern: &str, case_sensitive: bool) -> Vec<String> {
+    let mut variants = vec![String::new()];
+    for c in pattern.chars() {
+        let forms = case_variants(c, case_sensitive);
+        variants = variants
+            .iter()
+            .flat_map(|variant| {
+                forms.iter().map(move |&form| {
+                    let mut next = variant.clone();
+                    next.push(form);
+                    next
+                })
+            })
+            .collect();
+    }
+    variants
+}
+
+/// The half-open ranges of big-endian 32-byte key values whose base58
+/// encoding starts with `prefix`, one per possible encoded length.
+///
+/// A run of `k` leading `1`s stands for exactly `k` leading zero bytes, so
+/// the rest of the prefix has to be the leading digits of a value that is
+/// exactly `32 - k` bytes long.
+pub fn prefix_ranges(prefix: &str) -> Vec<(BigUint, BigUint)> {
+    let ones = prefix.bytes().take_while(|&b| b == b'1').count();
+    let rest = &prefix.as_bytes()[ones..];
+    if ones > PUBKEY_BYTES || (ones == PUBKEY_BYTES && !rest.is_empty()) {
+        return Vec::new();
+    }
+
+    let one = BigUint::from(1u8);
+    let hi = &one << (8 * (PUBKEY_BYTES - ones));
+    if rest.is_empty() {
+        // At least `ones` zero bytes; anything may follow.
+        return vec![(BigUint::from(0u8), hi)];
+    }
+    let lo = &one << (8 * (PUBKEY_BYTES - ones - 1));
+
+    let mut value = BigUint::from(0u8);
+    for &c in rest {
+        match digit(c) {
+            Some(d) => value = value * 58u32 + d,
+            None => return Vec::new(),
+        }
+    }
+
+    let mut ranges = Vec::new();
+    let mut scale = BigUint::from(1u8);
+    loop {
+        let start = &value * &scale;
+        if start >= hi {
+            break;
+        }
+        let end = (&value + 1u32) * &scale;
+        if end > lo {
+            let start = start.max(lo.clone());
+            let end = end.min(hi.clone());
+            ranges.push((start, end));
+        }
+        scale *= 58u32;
+    }
+    ranges
+}
+
+/// The characters `c` may appear as in an address.
+fn case_variants(c: char, case_sensitive: bool) -> Vec<char> {
+    let mut forms = vec![c];
+    if !case_sensitive {
+        for form in [c.to_ascii_lowercase(), c.to_ascii_uppercase()] {
+            if !forms.contains(&form) {
+                forms.push(form);
+            }
+        }
+    }
+    forms.retain(|&form| form.is_ascii() && digit(form as u8).is_some());
+    forms
+}
+
+fn invalid_char_message(c: char, column: usize) -> String {
+    if !c.is_ascii() {
+        return format!(
+            "`{}` (character {}) is not ASCII; addresses only use the base58 alphabet {}",
+            c,
+            column,
+            String::from_utf8_lossy(ALPHABET)
+        );
+    }
+    let suggestions: &[&str] = match c {
+        '0' => &["o"],
+        'O' => &["o", "Q", "D"],
+        'I' => &["i", "1", "L"],
+        'l' => &["L", "1", "i"],
+        _ => &[],
+    };
+    let mut message = format!(
+        "`{}` (character {}) is not in the base58 alphabet",
+        c, column
+    );
+    if !suggestions.is_empty() {
+        let quoted: Vec<String> = suggestions.iter().map(|s| format!("`{}`", s)).collect();
+        message.push_str(&format!("; try {} instead", quoted.join(" or ")));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::bs58;
+
+    fn error(pattern: &str) -> String {
+        validate_pattern(pattern, MatchPosition::Anywhere, true).unwrap_err()
+    }
+
+    #[test]
+    fn lookalike_characters_get_suggestions() {
+        assert!(error("S0l").contains("character 2") && error("S0l").contains("try `o` instead"));
+        assert!(error("SOL").contains("try `o` or `Q` or `D` instead"));
+        assert!(error("Ix").contains("try `i` or `1` or `L` instead"));
+        assert!(error("xl").contains("try `L` or `1` or `i` instead"));
+        assert!(!error("a+").contains("try"));
+        assert!(error("Sôl").contains("not ASCII"));
+
+        // Ignoring case, `O` can be spelled `o` and `l` can be `L`; `0` has
+        // no spelling at all.
+        assert!(validate_pattern("SOl", MatchPosition::Prefix, false).is_ok());
+        assert!(validate_pattern("S0l", MatchPosition::Prefix, false).is_err());
+    }
+
+    #[test]
+    fn impossible_prefixes_are_rejected() {
+        // The largest key encodes as `JEKNVnkbo3jma5nREBBJCDoXFVeKkD56V3xKrvRmWxFG`,
+        // so no 44-character address starts any higher.
+        let max = bs58::encode([0xff; PUBKEY_BYTES]).into_string();
+        assert_eq!(max.len(), 44);
+        assert!(validate_pattern(&max, MatchPosition::Prefix, true).is_ok());
+        let over = format!("{}z", &max[..43]);
+        assert!(validate_pattern(&over, MatchPosition::Prefix, true).is_err());
+        assert!(validate_pattern(&over, MatchPosition::Suffix, true).is_ok());
+        assert!(validate_pattern(&"z".repeat(45), MatchPosition::Both, true).is_err());
+
+        // Each leading `1` is a zero byte, and a key has only 32.
+        assert!(validate_pattern(&"1".repeat(32), MatchPosition::Prefix, true).is_ok());
+        assert!(validate_pattern(&"1".repeat(33), MatchPosition::Prefix, true).is_err());
+        assert!(prefix_ranges(&format!("{}2", "1".repeat(32))).is_empty());
+    }
+
+    #[test]
+    fn prefix_ranges_hold_exactly_the_matching_keys() {
+        assert_eq!(
+            prefix_ranges("1"),
+            vec![(BigUint::from(0u8), BigUint::from(1u8) << 248)]
+        );
+        for prefix in ["2", "SoL", "1z", "11A"] {
+            let ranges = prefix_ranges(prefix);
+            assert!(!ranges.is_empty(), "{}", prefix);
+            for (start, end) in ranges {
+                let last = &end - 1u32;
+                for value in [start, last] {
+                    let mut key = [0u8; PUBKEY_BYTES];
+                    let bytes = value.to_bytes_be();
+                    key[PUBKEY_BYTES - bytes.len()..].copy_from_slice(&bytes);
+                    let address = bs58::encode(key).into_string();
+                    assert!(address.starts_with(prefix), "{} {}", prefix, address);
+                }
+            }
+        }
+        assert_eq!(prefix_variants("a1", false), vec!["a1", "A1"]);
+        assert_eq!(prefix_variants("lo", false), vec!["Lo"]);
+        assert_eq!(prefix_variants("ab", true), vec!["ab"]);
+        // Each spelling once, however the pattern was typed.
+        assert_eq!(prefix_variants("SL", false), vec!["SL", "sL"]);
+    }
+}
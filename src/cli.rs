@@ -0,0 +1,602 @@
+use crate::{
+    matcher::MatchPosition,
+    mnemonic::WordCount,
+    output::{Output, OutputFormat, WriteMode},
+    pda::{PdaSettings, SeedSpec},
+    seal::Recipient,
+    search::{Generator, SearchConfig, Target},
+    server::Schedule,
+    split_key::{Offset, PartialKey},
+    with_seed::{WithSeedSettings, DEFAULT_ALPHABET, DEFAULT_TEMPLATE},
+};
+use clap::{Args, Parser, Subcommand};
+use solana_sdk::pubkey::Pubkey;
+use std::{fs, net::SocketAddr, ops::RangeInclusive, path::PathBuf, thread, time::Duration};
+
+/// Search for Solana keypairs whose address contains a vanity string.
+///
+/// Run without any arguments to be prompted for each setting instead.
+#[derive(Parser, Debug)]
+#[command(
+    name = "vanaddy",
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Vanity strings to search for (1-9 characters each), optionally as
+    /// `PATTERN:COUNT` to set how many wallets to find for that pattern
+    #[arg(value_name = "PATTERN", required_unless_present = "patterns_file")]
+    pub patterns: Vec<String>,
+
+    /// Read more patterns from a file, one `PATTERN[:COUNT]` per line
+    #[arg(long, value_name = "PATH")]
+    pub patterns_file: Option<PathBuf>,
+
+    /// Match the vanity string regardless of case
+    #[arg(short = 'i', long)]
+    pub ignore_case: bool,
+
+    /// Where in the address the vanity string has to appear
+    #[arg(short, long, value_enum, default_value_t = MatchPosition::Prefix)]
+    pub position: MatchPosition,
+
+    /// Treat the patterns as regular expressions over the whole address,
+    /// e.g. `^Sol.*[1-9]$`; use `^` and `$` instead of `--position`
+    #[arg(short = 'r', long, conflicts_with = "position")]
+    pub regex: bool,
+
+    /// How candidate keys are generated
+    #[arg(short, long, value_enum, default_value_t = Generator::Keypair)]
+    pub generator: Generator,
+
+    /// Length of the seed phrases generated by `--generator mnemonic`
+    /// [default: 12]
+    #[arg(long, value_enum, value_name = "N")]
+    pub words: Option<WordCount>,
+
+    /// Derive the keys of `--generator mnemonic` with a BIP39 passphrase,
+    /// asked for at startup or read from `VANADDY_PASSPHRASE`
+    #[arg(long)]
+    pub passphrase: bool,
+
+    /// Account indexes to check for each seed phrase of
+    /// `--generator mnemonic`, as `N` or an inclusive range `FIRST-LAST`;
+    /// account `i` is derived along `m/44'/501'/i'/0'`
+    #[arg(long, value_name = "RANGE", value_parser = parse_accounts)]
+    pub accounts: Option<RangeInclusive<u32>>,
+
+    /// Program to find vanity program derived addresses of with
+    /// `--generator pda`
+    #[arg(long, value_name = "PUBKEY")]
+    pub program_id: Option<Pubkey>,
+
+    /// A seed of the program address, in order: `str:TEXT`,
+    /// `pubkey:PUBKEY`, `hex:BYTES` or `u8`/`u16`/`u32`/`u64:N`, and
+    /// exactly one searched seed, `counter`, `random:N` or `suffix:TEXT`
+    #[arg(long = "seed", value_name = "SEED")]
+    pub seeds: Vec<SeedSpec>,
+
+    /// Base pubkey that signs for the accounts `--generator with-seed`
+    /// finds addresses for
+    #[arg(long, value_name = "PUBKEY")]
+    pub base: Option<Pubkey>,
+
+    /// Program that will own the accounts of `--generator with-seed`
+    #[arg(long, value_name = "PUBKEY")]
+    pub owner: Option<Pubkey>,
+
+    /// Seed of `--generator with-seed`, at most 32 bytes, with a `?` for
+    /// each character to search, e.g. `vault-??????`
+    /// [default: twelve `?`s]
+    #[arg(long, value_name = "TEMPLATE")]
+    pub seed_template: Option<String>,
+
+    /// Characters the `?`s of `--seed-template` are filled with
+    /// [default: letters and digits]
+    #[arg(long, value_name = "CHARS")]
+    pub seed_alphabet: Option<String>,
+
+    /// Someone else's partial key to find offsets for with
+    /// `--generator split-key`, created with `vanaddy split-key new`
+    #[arg(long, value_name = "KEY")]
+    pub partial_key: Option<PartialKey>,
+
+    /// Number of matching wallets to find for each pattern without a count
+    #[arg(short = 'n', long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    pub count: u64,
+
+    /// Number of worker threads [default: all available cores]; 0 with
+    /// `--listen` leaves the search to worker processes
+    #[arg(short = 't', long)]
+    pub threads: Option<u64>,
+
+    /// Also hand the search out to `vanaddy worker` processes connecting
+    /// to this address, e.g. `0.0.0.0:7878`; their matches go to this
+    /// process's outputs
+    #[arg(long, value_name = "ADDR", conflicts_with = "estimate")]
+    pub listen: Option<SocketAddr>,
+
+    /// Where to write matches; combine several with commas, e.g.
+    /// `csv,stdout`
+    #[arg(short, long, value_enum, value_delimiter = ',', default_value = "csv")]
+    pub format: Vec<OutputFormat>,
+
+    /// Path of the CSV results file
+    #[arg(short, long, default_value = "vanity_wallets.csv")]
+    pub output: PathBuf,
+
+    /// Path of the JSON Lines results file
+    #[arg(long, value_name = "PATH", default_value = "vanity_wallets.jsonl")]
+    pub jsonl_output: PathBuf,
+
+    /// Also write each match to `<PUBKEY>.json` in this directory, in the
+    /// `solana-keygen` keypair format
+    #[arg(long, value_name = "DIR")]
+    pub keypair_dir: Option<PathBuf>,
+
+    /// Also write each match to `<PUBKEY>.keystore.json` in this directory,
+    /// encrypted with a password asked for at startup (or taken from
+    /// `VANADDY_PASSWORD`)
+    #[arg(long, value_name = "DIR")]
+    pub keystore_dir: Option<PathBuf>,
+
+    /// Also write each match, encrypted to this public key, to
+    /// `--sealed-output`; repeat to add recipients. Create one with
+    /// `vanaddy keygen`
+    #[arg(long = "recipient", value_name = "KEY")]
+    pub recipients: Vec<Recipient>,
+
+    /// Path of the file of sealed JSON lines
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "vanity_wallets.sealed.jsonl"
+    )]
+    pub sealed_output: PathBuf,
+
+    /// Add to the results files if they already exist
+    #[arg(short, long, conflicts_with = "force")]
+    pub append: bool,
+
+    /// Overwrite the results files if they already exist
+    #[arg(long)]
+    pub force: bool,
+
+    /// Stop searching after this long, e.g. `90s`, `30m`, `12h` or `2d`
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub time_limit: Option<Duration>,
+
+    /// Lock the process's memory so keys are never written to swap; may
+    /// need a higher `ulimit -l`
+    #[arg(long)]
+    pub lock_memory: bool,
+
+    /// Benchmark this machine, print how long the search should take and exit
+    #[arg(long)]
+    pub estimate: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Decrypt a keystore file and print or save its secret key
+    #[command(visible_alias = "decrypt")]
+    Export(ExportArgs),
+    /// Create an identity for `--recipient` and print its public key
+    Keygen(KeygenArgs),
+    /// Decrypt sealed results with an identity and print them as JSON lines
+    Unseal(UnsealArgs),
+    /// Search for a coordinator started with `--listen` until it has every
+    /// match
+    Worker(WorkerArgs),
+    /// Have an untrusted machine search for a key only you can use
+    #[command(subcommand)]
+    SplitKey(SplitKeyCommand),
+    /// Run searches submitted over an HTTP/JSON API, sealing their results
+    Serve(ServeArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct ServeArgs {
+    /// Address to serve the API on
+    #[arg(long, value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    pub listen: SocketAddr,
+
+    /// Number of worker threads shared by the jobs [default: all available
+    /// cores]
+    #[arg(short = 't', long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub threads: Option<u64>,
+
+    /// Whether jobs run one after another or take turns
+    #[arg(long, value_enum, default_value_t = Schedule::Queue)]
+    pub schedule: Schedule,
+}
+
+#[derive(Args, Debug)]
+pub struct WorkerArgs {
+    /// Address of the coordinator, e.g. `10.0.0.2:7878`
+    pub coordinator: String,
+
+    /// Number of worker threads [default: all available cores]
+    #[arg(short = 't', long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub threads: Option<u64>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SplitKeyCommand {
+    /// Create a split-key secret and print the partial key to hand to a
+    /// searcher running `--generator split-key --partial-key KEY`
+    New(SplitKeyNewArgs),
+    /// Add an offset a searcher found to the secret and print or save the
+    /// expanded key of the address
+    Combine(SplitKeyCombineArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct SplitKeyNewArgs {
+    /// Write the secret to this file, readable only by its owner, instead
+    /// of standard output
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct SplitKeyCombineArgs {
+    /// Secret file created by `vanaddy split-key new`
+    #[arg(short, long, value_name = "PATH")]
+    pub secret: PathBuf,
+
+    /// Offset from the searcher's results
+    pub offset: Offset,
+
+    /// Fail unless the key is for this address, as listed next to the
+    /// offset
+    #[arg(long, value_name = "PUBKEY")]
+    pub address: Option<Pubkey>,
+
+    /// Encrypt the key into this keystore file, with a password asked for
+    /// (or taken from `VANADDY_PASSWORD`), instead of printing it
+    #[arg(long, value_name = "PATH", conflicts_with = "output")]
+    pub keystore: Option<PathBuf>,
+
+    /// Write the base58 expanded key to this file, readable only by its
+    /// owner, instead of standard output
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct KeygenArgs {
+    /// Write the identity to this file, readable only by its owner, instead
+    /// of standard output
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+pub struct UnsealArgs {
+    /// Identity file created by `vanaddy keygen`
+    #[arg(short, long, value_name = "PATH")]
+    pub identity: PathBuf,
+
+    /// File written by `--recipient`
+    pub sealed: PathBuf,
+}
+
+#[derive(Args, Debug)]
+pub struct ExportArgs {
+    /// Keystore file written by `--keystore-dir`
+    pub keystore: PathBuf,
+
+    /// Print the base58 secret key instead of a `solana-keygen` JSON keypair
+    #[arg(long)]
+    pub base58: bool,
+
+    /// Write to this file, readable only by its owner, instead of standard
+    /// output
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn into_config(self) -> Result<SearchConfig, String> {
+        let mut specs = self.patterns;
+        if let Some(path) = &self.patterns_file {
+            let contents = fs::read_to_string(path)
+                .map_err(|e| format!("cannot read `{}`: {}", path.display(), e))?;
+            specs.extend(
+                contents
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(str::to_owned),
+            );
+        }
+        let targets = specs
+            .iter()
+            .map(|spec| parse_target(spec, self.count))
+            .collect::<Result<_, _>>()?;
+
+        if self.generator != Generator::Mnemonic
+            && (self.words.is_some() || self.passphrase || self.accounts.is_some())
+        {
+            return Err(
+                "`--words`, `--passphrase` and `--accounts` need `--generator mnemonic`"
+                    .to_string(),
+            );
+        }
+        if self.generator != Generator::Pda && (self.program_id.is_some() || !self.seeds.is_empty())
+        {
+            return Err("`--program-id` and `--seed` need `--generator pda`".to_string());
+        }
+        if self.generator != Generator::SplitKey && self.partial_key.is_some() {
+            return Err("`--partial-key` needs `--generator split-key`".to_string());
+        }
+        if self.generator != Generator::WithSeed
+            && (self.base.is_some()
+                || self.owner.is_some()
+                || self.seed_template.is_some()
+                || self.seed_alphabet.is_some())
+        {
+            return Err(
+                "`--base`, `--owner`, `--seed-template` and `--seed-alphabet` need `--generator with-seed`"
+                    .to_string(),
+            );
+        }
+
+        let mut formats = self.format;
+        if self.keypair_dir.is_some() && !formats.contains(&OutputFormat::Keypair) {
+            formats.push(OutputFormat::Keypair);
+        }
+        if self.keystore_dir.is_some() && !formats.contains(&OutputFormat::Keystore) {
+            formats.push(OutputFormat::Keystore);
+        }
+        if !self.recipients.is_empty() && !formats.contains(&OutputFormat::Sealed) {
+            formats.push(OutputFormat::Sealed);
+        }
+        let mut outputs = Vec::new();
+        for format in formats {
+            let output = match format {
+                OutputFormat::Csv => Output::Csv(self.output.clone()),
+                OutputFormat::Jsonl => Output::JsonLines(self.jsonl_output.clone()),
+                OutputFormat::Stdout => Output::Stdout,
+                OutputFormat::Keypair => Output::KeypairDir(
+                    self.keypair_dir
+                        .clone()
+                        .ok_or("`--format keypair` needs `--keypair-dir`")?,
+                ),
+                OutputFormat::Keystore => Output::KeystoreDir(
+                    self.keystore_dir
+                        .clone()
+                        .ok_or("`--format keystore` needs `--keystore-dir`")?,
+                ),
+                OutputFormat::Sealed if self.recipients.is_empty() => {
+                    return Err("`--format sealed` needs at least one `--recipient`".to_string())
+                }
+                OutputFormat::Sealed => Output::Sealed {
+                    path: self.sealed_output.clone(),
+                    recipients: self.recipients.clone(),
+                },
+            };
+            if !outputs.contains(&output) {
+                outputs.push(output);
+            }
+        }
+
+        let config = SearchConfig {
+            case_sensitive: !self.ignore_case,
+            position: self.position,
+            regex: self.regex,
+            word_count: self.words.unwrap_or(WordCount::Twelve),
+            passphrase: self.passphrase,
+            accounts: self.accounts.unwrap_or(0..=0),
+            program_address: self.program_id.map(|program_id| PdaSettings {
+                program_id,
+                seeds: self.seeds,
+            }),
+            with_seed: match (self.base, self.owner) {
+                (Some(base), Some(owner)) => Some(WithSeedSettings {
+                    base,
+                    owner,
+                    template: self
+                        .seed_template
+                        .unwrap_or_else(|| DEFAULT_TEMPLATE.to_string()),
+                    alphabet: self
+                        .seed_alphabet
+                        .unwrap_or_else(|| DEFAULT_ALPHABET.to_string()),
+                }),
+                _ => None,
+            },
+            partial_key: self.partial_key,
+            max_threads: self
+                .threads
+                .map_or_else(default_thread_count, |threads| threads as usize),
+            listen: self.listen,
+            outputs,
+            write_mode: if self.append {
+                WriteMode::Append
+            } else if self.force {
+                WriteMode::Overwrite
+            } else {
+                WriteMode::Create
+            },
+            time_limit: self.time_limit,
+            lock_memory: self.lock_memory,
+            ..SearchConfig::new(targets, self.generator)
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// Parses a `PATTERN` or `PATTERN:COUNT` argument, falling back to
+/// `default_count` when no count is given. Only a trailing run of digits
+/// counts, so regexes such as `(?:ab)` are left intact.
+pub fn parse_target(spec: &str, default_count: u64) -> Result<Target, String> {
+    let (pattern, count) = match spec
+        .rsplit_once(':')
+        .filter(|(_, count)| !count.is_empty() && count.bytes().all(|b| b.is_ascii_digit()))
+    {
+        Some((pattern, count)) => {
+            let count: u64 = count
+                .parse()
+                .map_err(|_| format!("invalid count `{}` in `{}`", count, spec))?;
+            if count == 0 {
+                return Err(format!("count in `{}` must be at least 1", spec));
+            }
+            (pattern, count)
+        }
+        None => (spec, default_count),
+    };
+    Ok(Target {
+        pattern: pattern.to_owned(),
+        count,
+    })
+}
+
+pub fn validate_vanity_string(vanity_string: &str) -> Result<(), String> {
+    let len = vanity_string.chars().count();
+    if !(1..=9).contains(&len) {
+        return Err(format!("vanity string must be 1-9 characters, got {}", len));
+    }
+    Ok(())
+}
+
+pub fn default_thread_count() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Parses a duration given as a number with an optional `s`, `m`, `h` or `d`
+/// unit suffix. A bare number is taken as seconds.
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let (digits, unit) = match input.find(|c: char| !c.is_ascii_digit()) {
+        Some(split) => input.split_at(split),
+        None => (input, "s"),
+    };
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!("invalid duration `{}`", input))?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return Err(format!("unknown duration unit `{}` in `{}`", unit, input)),
+    };
+    if value == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    value
+        .checked_mul(multiplier)
+        .map(Duration::from_secs)
+        .ok_or_else(|| format!("duration `{}` is too large", input))
+}
+
+/// Parses an account index `N` or an inclusive range of them, `FIRST-LAST`.
+pub fn parse_accounts(input: &str) -> Result<RangeInclusive<u32>, String> {
+    let parse_index = |index: &str| {
+        index
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|&index| index < 1 << 31)
+            .ok_or_else(|| format!("invalid account index `{}`", index))
+    };
+    let (first, last) = match input.split_once('-') {
+        Some((first, last)) => (parse_index(first)?, parse_index(last)?),
+        None => {
+            let index = parse_index(input)?;
+            (index, index)
+        }
+    };
+    if first > last {
+        return Err(format!("account range `{}` is empty", input));
+    }
+    Ok(first..=last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_take_a_unit() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("5m"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("1h"), Ok(Duration::from_secs(3600)));
+        assert_eq!(parse_duration("2d"), Ok(Duration::from_secs(2 * 86_400)));
+        for bad in [
+            "5w",
+            "1.5h",
+            "m",
+            "",
+            "0s",
+            "-3s",
+            &format!("{}d", u64::MAX),
+        ] {
+            assert!(parse_duration(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn targets_take_an_optional_count() {
+        let target = |pattern: &str, count| Target {
+            pattern: pattern.to_string(),
+            count,
+        };
+        assert_eq!(parse_target("Sol", 3), Ok(target("Sol", 3)));
+        assert_eq!(parse_target("Sol:5", 3), Ok(target("Sol", 5)));
+        assert_eq!(parse_target("(?:ab)", 1), Ok(target("(?:ab)", 1)));
+        assert!(parse_target("Sol:0", 1).is_err());
+        assert!(parse_target("Sol:99999999999999999999", 1).is_err());
+        // An empty count is no count, and the `:` is left to the pattern
+        // checks, which reject it.
+        assert_eq!(parse_target("SoL:", 1), Ok(target("SoL:", 1)));
+        let config = |spec| {
+            Cli::try_parse_from(["vanaddy", spec])
+                .unwrap()
+                .into_config()
+        };
+        assert!(config("SoL:2").is_ok());
+        assert!(config("SoL:").is_err());
+    }
+
+    #[test]
+    fn account_ranges_are_inclusive() {
+        assert_eq!(parse_accounts("0-10"), Ok(0..=10));
+        assert_eq!(parse_accounts(" 3 - 4 "), Ok(3..=4));
+        assert_eq!(parse_accounts("5"), Ok(5..=5));
+        assert_eq!(parse_accounts("7-7"), Ok(7..=7));
+        // Hardened indexes stop at 2^31 - 1.
+        assert_eq!(parse_accounts("2147483647"), Ok(2147483647..=2147483647));
+        for bad in [
+            "10-0",
+            "2147483648",
+            "0-4294967296",
+            "-1",
+            "0..10",
+            "a",
+            "",
+            "1-",
+        ] {
+            assert!(parse_accounts(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn vanity_strings_are_checked() {
+        assert!(validate_vanity_string("Sol").is_ok());
+        assert!(validate_vanity_string("123456789").is_ok());
+        assert!(validate_vanity_string("").is_err());
+        assert!(validate_vanity_string("1234567890").is_err());
+        for bad in ["S0L", "Il", "abc!", "SoL ana", "Sôn"] {
+            let config = Cli::try_parse_from(["vanaddy", bad]).unwrap().into_config();
+            assert!(config.is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,620 @@
+//! Spreading one search over many machines.
+//!
+//! A coordinator is an ordinary search that also listens for
+//! `vanaddy worker` processes. Each worker connects, says which protocol
+//! it speaks and gets the job: the patterns, how they are matched, the
+//! generator and the matches still wanted. It then runs the same
+//! [`spawn_threads`](search::spawn_threads) loop as a local search, sending every match as it is
+//! found and its key count every [`PROGRESS_INTERVAL`]. The coordinator
+//! answers each progress report with the matches still wanted, so workers
+//! drop patterns others have finished, or with `stop` once the search is
+//! over, after which the worker reports the keys of its last batch.
+//!
+//! The coordinator checks every match before claiming it: the key has to
+//! give the address, and the address has to match. Claims go through the
+//! coordinator's [`SearchState`], so the results hold exactly the number
+//! of wallets asked for however many workers hit at once, and they go to
+//! the coordinator's outputs like local ones.
+//!
+//! Messages are JSON, one per line:
+//!
+//! ```json
+//! {"hello":{"protocol":1}}
+//! {"job":{"patterns":["Sol"],"remaining":[1],"case_sensitive":true,"position":"prefix","regex":false,"generator":"keypair","partial_key":null}}
+//! {"progress":{"generated":2000000}}
+//! {"found":{"pattern":0,"pubkey":"Sol...","thread_id":3,"secret":"...","offset":null}}
+//! {"status":{"remaining":[1]}}
+//! "stop"
+//! {"progress":{"generated":1000}}
+//! ```
+//!
+//! Nothing is encrypted or authenticated: anyone who can reach the port
+//! can join as a worker or read the job, and matches of the keypair and
+//! scalar-walk generators carry their secret key in the clear, so only run
+//! those over a network you trust. Sealing with `--recipient` only
+//! protects the results file, not the wire. Split-key matches carry just
+//! the offset, which signs for nothing without the requester's secret.
+
+use crate::{
+    expanded_key::ExpandedKeypair,
+    matcher::{MatchPosition, PatternSet},
+    search::{self, FoundKey, Generator, SearchConfig, Target},
+    split_key::{Offset, PartialKey, SplitMatch},
+    state::SearchState,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use solana_sdk::{
+    bs58,
+    signature::Signer,
+    signer::keypair::{keypair_from_seed, Keypair},
+};
+use std::{
+    borrow::Cow,
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant},
+};
+use zeroize::Zeroizing;
+
+/// Bumped whenever the messages change.
+const PROTOCOL: u32 = 1;
+
+/// How often workers report their key count, and learn whether to go on.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long either side waits for the other before giving up on it.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longer lines than this are not ours. Line buffers are allocated this
+/// big up front so they never move and leave secrets behind.
+const MAX_LINE_LEN: usize = 64 * 1024;
+
+/// How often the coordinator checks for new workers.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What a worker needs to search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Job {
+    pub patterns: Vec<String>,
+    /// Matches still wanted for each pattern when the worker joined.
+    pub remaining: Vec<u64>,
+    pub case_sensitive: bool,
+    pub position: String,
+    pub regex: bool,
+    pub generator: String,
+    pub partial_key: Option<String>,
+}
+
+impl Job {
+    /// The job of `config`, before anything is found.
+    pub fn new(config: &SearchConfig) -> Self {
+        Job {
+            patterns: config.targets.iter().map(|t| t.pattern.clone()).collect(),
+            remaining: config.counts(),
+            case_sensitive: config.case_sensitive,
+            position: config.position.as_str().to_owned(),
+            regex: config.regex,
+            generator: value_name(config.generator),
+            partial_key: config.partial_key.map(|key| key.to_string()),
+        }
+    }
+
+    /// The search this job describes, run on `threads` threads.
+    fn config(&self, threads: usize) -> Result<SearchConfig, String> {
+        if self.patterns.len() != self.remaining.len() || self.patterns.is_empty() {
+            return Err("the job has no patterns".to_string());
+        }
+        let generator = Generator::from_str(&self.generator, false)?;
+        if !matches!(
+            generator,
+            Generator::Keypair | Generator::ScalarWalk | Generator::SplitKey
+        ) {
+            return Err(format!(
+                "generator `{}` can't be distributed",
+                self.generator
+            ));
+        }
+        let partial_key = self
+            .partial_key
+            .as_deref()
+            .map(str::parse::<PartialKey>)
+            .transpose()?;
+        if generator == Generator::SplitKey && partial_key.is_none() {
+            return Err("the split-key job has no partial key".to_string());
+        }
+        let targets = self
+            .patterns
+            .iter()
+            .zip(&self.remaining)
+            .map(|(pattern, &count)| Target {
+                pattern: pattern.clone(),
+                count,
+            })
+            .collect();
+        let config = SearchConfig {
+            case_sensitive: self.case_sensitive,
+            position: MatchPosition::from_str(&self.position, false)?,
+            regex: self.regex,
+            partial_key,
+            max_threads: threads,
+            ..SearchConfig::new(targets, generator)
+        };
+        config.validate_patterns()?;
+        Ok(config)
+    }
+}
+
+fn value_name(value: impl ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .expect("no variants are skipped")
+        .get_name()
+        .to_owned()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum WorkerMessage<'a> {
+    Hello {
+        protocol: u32,
+    },
+    /// Keys generated since the last report.
+    Progress {
+        generated: u64,
+    },
+    Found {
+        pattern: usize,
+        pubkey: String,
+        thread_id: usize,
+        /// The base58 secret key, borrowed from the buffer the line was
+        /// read into so no unwiped copy is made.
+        #[serde(borrow)]
+        secret: Option<Cow<'a, str>>,
+        offset: Option<String>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum CoordinatorMessage {
+    Job(Job),
+    Status { remaining: Vec<u64> },
+    Stop,
+    Error { message: String },
+}
+
+/// Writes `message` as one line, through a buffer that is wiped.
+fn send(stream: &mut TcpStream, message: &impl Serialize) -> io::Result<()> {
+    let mut line = Zeroizing::new(Vec::with_capacity(512));
+    serde_json::to_writer(&mut *line, message).map_err(io::Error::other)?;
+    line.push(b'\n');
+    stream.write_all(&line)
+}
+
+/// Reads one line into `line`, replacing what it held.
+///
+/// Lines are read a byte at a time rather than through a `BufReader`,
+/// whose buffer would keep copies of secret keys; messages are few and
+/// short, so the extra reads don't matter.
+fn receive_line(stream: &mut TcpStream, line: &mut Zeroizing<Vec<u8>>) -> io::Result<()> {
+    line.clear();
+    let mut byte = [0u8];
+    loop {
+        if stream.read(&mut byte)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if byte[0] == b'\n' {
+            return Ok(());
+        }
+        if line.len() == MAX_LINE_LEN {
+            return Err(invalid("message too long"));
+        }
+        line.push(byte[0]);
+    }
+}
+
+fn parse<'a, T: Deserialize<'a>>(line: &'a [u8]) -> io::Result<T> {
+    serde_json::from_slice(line).map_err(|e| invalid(format!("bad message: {}", e)))
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+/// Accepts workers on `listener` until `state` says the search is over,
+/// serving each on its own thread. Their matches are claimed in `state`
+/// and sent to `tx`.
+pub fn serve(
+    listener: TcpListener,
+    job: Job,
+    patterns: PatternSet,
+    state: Arc<SearchState>,
+    tx: mpsc::Sender<FoundKey>,
+) -> io::Result<thread::JoinHandle<()>> {
+    listener.set_nonblocking(true)?;
+    let job = Arc::new(job);
+    Ok(thread::spawn(move || {
+        while !state.is_finished() {
+            let (stream, peer) = match listener.accept() {
+                Ok(accepted) => accepted,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(ACCEPT_INTERVAL);
+                    continue;
+                }
+                Err(e) => {
+                    eprintln!("\nerror: cannot accept worker: {}", e);
+                    continue;
+                }
+            };
+            let (job, patterns, state, tx) =
+                (job.clone(), patterns.clone(), state.clone(), tx.clone());
+            thread::spawn(move || {
+                if let Err(e) = serve_worker(stream, &job, &patterns, &state, &tx) {
+                    eprintln!("\nworker {} left: {}", peer, e);
+                }
+            });
+        }
+        // Workers that connected just as the search ended are sent home
+        // rather than left waiting for a job.
+        while let Ok((mut stream, _)) = listener.accept() {
+            let _ = stream
+                .set_nonblocking(false)
+                .and_then(|()| send(&mut stream, &CoordinatorMessage::Stop));
+        }
+    }))
+}
+
+fn serve_worker(
+    mut stream: TcpStream,
+    job: &Job,
+    patterns: &PatternSet,
+    state: &SearchState,
+    tx: &mpsc::Sender<FoundKey>,
+) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut line = Zeroizing::new(Vec::with_capacity(MAX_LINE_LEN));
+
+    receive_line(&mut stream, &mut line)?;
+    match parse(&line)? {
+        WorkerMessage::Hello { protocol } if protocol == PROTOCOL => {}
+        WorkerMessage::Hello { protocol } => {
+            let message = format!(
+                "the coordinator speaks protocol {}, not {}; run the same version",
+                PROTOCOL, protocol
+            );
+            send(
+                &mut stream,
+                &CoordinatorMessage::Error {
+                    message: message.clone(),
+                },
+            )?;
+            return Err(invalid(message));
+        }
+        _ => return Err(invalid("expected hello")),
+    }
+    let job = Job {
+        remaining: state.remaining(),
+        ..job.clone()
+    };
+    send(&mut stream, &CoordinatorMessage::Job(job.clone()))?;
+
+    loop {
+        receive_line(&mut stream, &mut line)?;
+        match parse(&line)? {
+            WorkerMessage::Progress { generated } => {
+                state.add_remote(generated);
+                if state.is_finished() {
+                    send(&mut stream, &CoordinatorMessage::Stop)?;
+                    // The worker answers with the keys of its last batch.
+                    receive_line(&mut stream, &mut line)?;
+                    if let WorkerMessage::Progress { generated } = parse(&line)? {
+                        state.add_remote(generated);
+                    }
+                    return Ok(());
+                }
+                let remaining = state.remaining();
+                send(&mut stream, &CoordinatorMessage::Status { remaining })?;
+            }
+            WorkerMessage::Found {
+                pattern,
+                pubkey,
+                thread_id,
+                secret,
+                offset,
+            } => {
+                let (found, hits) = check_match(&job, patterns, pattern, pubkey, secret, offset)
+                    .map_err(invalid)?;
+                if let Some(index) = state.claim(&hits) {
+                    let found = FoundKey {
+                        pattern: patterns.pattern(index).to_owned(),
+                        worker: thread_id,
+                        attempts: state.generated(),
+                        ..found
+                    };
+                    if tx.send(found).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            WorkerMessage::Hello { .. } => return Err(invalid("unexpected hello")),
+        }
+    }
+}
+
+/// Rebuilds a worker's match and checks it: the key has to give the
+/// address, and the address has to match the pattern it was sent for.
+/// Returns the match and every pattern the address matches.
+fn check_match(
+    job: &Job,
+    patterns: &PatternSet,
+    pattern: usize,
+    pubkey: String,
+    secret: Option<Cow<'_, str>>,
+    offset: Option<String>,
+) -> Result<(FoundKey, Vec<usize>), String> {
+    let found = match (job.generator.as_str(), secret, offset) {
+        ("split-key", None, Some(offset)) => {
+            let partial_key: PartialKey = job
+                .partial_key
+                .as_deref()
+                .ok_or("split-key job without a partial key")?
+                .parse()?;
+            let offset: Offset = offset.parse()?;
+            let split_key = SplitMatch {
+                partial_key,
+                offset,
+            };
+            let address = partial_key.address(&offset).to_string();
+            FoundKey::split_key(split_key, address, "", 0, 0)
+        }
+        (generator @ ("keypair" | "scalar-walk"), Some(secret), None) => {
+            let bytes = Zeroizing::new(
+                bs58::decode(secret.as_ref())
+                    .into_vec()
+                    .map_err(|e| format!("invalid secret key: {}", e))?,
+            );
+            if generator == "keypair" {
+                let keypair: Keypair = bytes
+                    .get(..32)
+                    .and_then(|seed| keypair_from_seed(seed).ok())
+                    .ok_or("invalid secret key")?;
+                if bytes.len() != 64 || bytes[32..] != keypair.pubkey().to_bytes() {
+                    return Err("secret key doesn't hold its public key".to_string());
+                }
+                let address = keypair.pubkey().to_string();
+                FoundKey::new(&keypair, address, "", 0, 0)
+            } else {
+                let keypair = ExpandedKeypair::from_bytes(&bytes)?;
+                let address = keypair.pubkey().to_string();
+                FoundKey::expanded(&keypair, address, "", 0, 0)
+            }
+        }
+        _ => return Err("match doesn't fit the job's generator".to_string()),
+    };
+    if found.public_key != pubkey {
+        return Err(format!(
+            "the key is for {}, not {}",
+            found.public_key, pubkey
+        ));
+    }
+    let hits = patterns.matches(&pubkey);
+    if !hits.contains(&pattern) {
+        return Err(format!("{} doesn't match pattern {}", pubkey, pattern));
+    }
+    Ok((found, hits))
+}
+
+/// Connects to the coordinator at `address` and searches on `threads`
+/// threads until it says to stop. Returns the keys generated.
+pub fn run_worker(address: impl ToSocketAddrs, threads: usize) -> io::Result<u64> {
+    let mut stream = TcpStream::connect(address)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_nodelay(true)?;
+    let mut line = Zeroizing::new(Vec::with_capacity(MAX_LINE_LEN));
+
+    send(&mut stream, &WorkerMessage::Hello { protocol: PROTOCOL })?;
+    receive_line(&mut stream, &mut line)?;
+    let job = match parse(&line)? {
+        CoordinatorMessage::Job(job) => job,
+        CoordinatorMessage::Stop => return Ok(0),
+        CoordinatorMessage::Error { message } => return Err(io::Error::other(message)),
+        _ => return Err(invalid("expected a job")),
+    };
+    let config = job.config(threads).map_err(invalid)?;
+    let patterns = config.patterns();
+    let mut remaining = job.remaining;
+    let mut total = 0;
+
+    // Each round searches for what the coordinator still wants. A round
+    // ends early when this worker has found all of that, but others' finds
+    // went to the results first; the next round goes on with the rest.
+    loop {
+        let state = Arc::new(SearchState::new(remaining.clone(), threads));
+        let (tx, rx) = mpsc::channel();
+        let handles =
+            search::spawn_threads(config.key_source(None), patterns.clone(), state.clone(), tx);
+        let mut reported = 0;
+        let mut next_report = Instant::now() + PROGRESS_INTERVAL;
+
+        let stopped = loop {
+            let wait = next_report.saturating_duration_since(Instant::now());
+            let workers_done = match rx.recv_timeout(wait) {
+                Ok(found) => {
+                    send_found(&mut stream, &found, &patterns)?;
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Timeout) => false,
+                Err(mpsc::RecvTimeoutError::Disconnected) => true,
+            };
+
+            let generated = state.generated();
+            send(
+                &mut stream,
+                &WorkerMessage::Progress {
+                    generated: generated - reported,
+                },
+            )?;
+            reported = generated;
+            receive_line(&mut stream, &mut line)?;
+            match parse(&line)? {
+                CoordinatorMessage::Status { remaining: wanted } => {
+                    for (index, &count) in wanted.iter().enumerate() {
+                        if count == 0 && index < patterns.len() {
+                            state.retire(index);
+                        }
+                    }
+                    remaining = wanted;
+                }
+                CoordinatorMessage::Stop => {
+                    state.stop();
+                    break true;
+                }
+                CoordinatorMessage::Error { message } => return Err(io::Error::other(message)),
+                CoordinatorMessage::Job(_) => return Err(invalid("unexpected job")),
+            }
+            if workers_done {
+                break false;
+            }
+            next_report = Instant::now() + PROGRESS_INTERVAL;
+        };
+        for handle in handles {
+            let _ = handle.join();
+        }
+        total += state.generated();
+        if stopped {
+            send(
+                &mut stream,
+                &WorkerMessage::Progress {
+                    generated: state.generated() - reported,
+                },
+            )?;
+            return Ok(total);
+        }
+        if remaining.len() != patterns.len() {
+            return Err(invalid("the coordinator's status doesn't fit the job"));
+        }
+    }
+}
+
+fn send_found(stream: &mut TcpStream, found: &FoundKey, patterns: &PatternSet) -> io::Result<()> {
+    let index = (0..patterns.len())
+        .find(|&i| patterns.pattern(i) == found.pattern)
+        .expect("matches are for one of the patterns");
+    let secret = found.secret_key.as_ref().map(|key| key.to_base58());
+    send(
+        stream,
+        &WorkerMessage::Found {
+            pattern: index,
+            pubkey: found.public_key.clone(),
+            thread_id: found.worker,
+            secret: secret.as_ref().map(|secret| Cow::Borrowed(secret.as_str())),
+            offset: found.split_key.map(|split| split.offset.to_string()),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{output::Output, split_key::SplitSecret};
+
+    fn coordinate(config: SearchConfig, workers: usize) -> (Vec<FoundKey>, Arc<SearchState>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let state = Arc::new(SearchState::new(config.counts(), 0));
+        let (tx, rx) = mpsc::channel();
+        let job = Job::new(&config);
+        let server = serve(listener, job, config.patterns(), state.clone(), tx).unwrap();
+
+        let handles: Vec<_> = (0..workers)
+            .map(|_| thread::spawn(move || run_worker(address, 2).unwrap()))
+            .collect();
+        let found: Vec<FoundKey> = rx.iter().collect();
+        server.join().unwrap();
+        let generated: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+        assert!(generated > 0);
+        assert_eq!(state.generated(), generated);
+        (found, state)
+    }
+
+    fn config(generator: Generator, partial_key: Option<PartialKey>) -> SearchConfig {
+        let mut config = Job {
+            patterns: vec!["1".to_string(), "A".to_string()],
+            remaining: vec![3, 2],
+            case_sensitive: false,
+            position: "anywhere".to_string(),
+            regex: false,
+            generator: value_name(generator),
+            partial_key: partial_key.map(|key| key.to_string()),
+        }
+        .config(0)
+        .unwrap();
+        config.outputs = vec![Output::Stdout];
+        config.listen = Some("127.0.0.1:0".parse().unwrap());
+        config.validate().unwrap();
+        config
+    }
+
+    #[test]
+    fn workers_find_exactly_the_target_count() {
+        let (found, state) = coordinate(config(Generator::Keypair, None), 3);
+
+        assert_eq!(found.len(), 5);
+        assert_eq!(found.iter().filter(|key| key.pattern == "1").count(), 3);
+        assert_eq!(state.found(), vec![3, 2]);
+        for key in &found {
+            let secret = key.secret_key.as_ref().unwrap();
+            let keypair = Keypair::from_bytes(secret.as_bytes()).unwrap();
+            assert_eq!(keypair.pubkey().to_string(), key.public_key);
+        }
+    }
+
+    #[test]
+    fn split_key_workers_send_offsets() {
+        let secret = SplitSecret::generate();
+        let (found, _) = coordinate(config(Generator::SplitKey, Some(secret.partial_key())), 2);
+
+        assert_eq!(found.len(), 5);
+        for key in &found {
+            assert!(key.secret_key.is_none());
+            let offset = key.split_key.unwrap().offset;
+            assert_eq!(secret.combine(&offset).pubkey().to_string(), key.public_key);
+        }
+    }
+
+    #[test]
+    fn forged_matches_are_rejected() {
+        let config = config(Generator::Keypair, None);
+        let job = Job::new(&config);
+        let patterns = config.patterns();
+        let keypair = Keypair::new();
+        let secret = keypair.to_base58_string();
+        let other = Keypair::new().pubkey().to_string();
+
+        let check = |pubkey: String, secret: &str| {
+            check_match(
+                &job,
+                &patterns,
+                0,
+                pubkey,
+                Some(Cow::Borrowed(secret)),
+                None,
+            )
+        };
+        assert!(check(other, &secret).is_err());
+        assert!(check(keypair.pubkey().to_string(), "11").is_err());
+        assert!(check_match(
+            &job,
+            &patterns,
+            0,
+            keypair.pubkey().to_string(),
+            None,
+            Some("00".repeat(32))
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::{
+    base58::{self, PUBKEY_BYTES},
+    matcher::{MatchPosition, PatternSet},
+    regex_pattern,
+};
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use std::time::Duration;
+
+/// Typical length of a base58-encoded public key. Most keys encode to 44
+/// characters and a few percent to 43, which only matters for `anywhere`.
+const TYPICAL_ADDRESS_LEN: usize = 44;
+
+/// How likely a single random keypair is to match a pattern.
+#[derive(Debug, Clone, Copy)]
+pub struct Difficulty {
+    probability: f64,
+}
+
+impl Difficulty {
+    /// Works out the per-key match probability for a pattern.
+    ///
+    /// Prefixes are measured exactly from the numeric ranges of keys that
+    /// encode to them, because the leading character of a 44-character
+    /// address is far from uniform. Later characters are close enough to
+    /// uniform that each one counts as `variants / 58`, where a
+    /// case-insensitive letter has one or two spellings in the alphabet.
+    pub fn new(pattern: &str, position: MatchPosition, case_sensitive: bool) -> Self {
+        let prefix = || prefix_probability(pattern, case_sensitive);
+        let uniform = uniform_probability(pattern, case_sensitive);
+
+        let probability = match position {
+            MatchPosition::Prefix => prefix(),
+            MatchPosition::Suffix => uniform,
+            MatchPosition::Both => prefix() * uniform,
+            MatchPosition::Anywhere => {
+                let windows = TYPICAL_ADDRESS_LEN.saturating_sub(pattern.len()) + 1;
+                1.0 - (1.0 - uniform).powi(windows as i32)
+            }
+        };
+        Difficulty { probability }
+    }
+
+    /// Works out the per-key match probability for a regular expression,
+    /// or `None` when it is too complex to compile into a DFA. The leading
+    /// character is weighted like a prefix; the rest count as uniform.
+    pub fn for_regex(pattern: &str, case_sensitive: bool) -> Option<Self> {
+        let mut leading = [0.0; 58];
+        for (weight, &c) in leading.iter_mut().zip(base58::ALPHABET.iter()) {
+            *weight = prefix_probability(&(c as char).to_string(), true);
+        }
+        let probability = regex_pattern::probability(pattern, case_sensitive, &leading)?;
+        Some(Difficulty { probability })
+    }
+
+    /// Difficulty of each pattern in a set, or `None` for regexes too
+    /// complex to estimate.
+    pub fn for_patterns(patterns: &PatternSet) -> Vec<Option<Self>> {
+        let case_sensitive = patterns.case_sensitive();
+        patterns
+            .patterns()
+            .iter()
+            .map(|pattern| match patterns.position() {
+                Some(position) => Some(Difficulty::new(pattern, position, case_sensitive)),
+                None => Difficulty::for_regex(pattern, case_sensitive),
+            })
+            .collect()
+    }
+
+    /// Chance that one random key matches.
+    pub fn probability(&self) -> f64 {
+        self.probability
+    }
+
+    /// Mean number of keys to generate before finding `count` matches.
+    pub fn expected_attempts(&self, count: u64) -> f64 {
+        count as f64 / self.probability
+    }
+
+    /// Chance of having found at least `count` matches after `attempts`
+    /// keys, treating matches as a Poisson process.
+    pub fn success_probability(&self, count: u64, attempts: f64) -> f64 {
+        let lambda = attempts * self.probability;
+        if count == 0 {
+            return 1.0;
+        }
+        if lambda <= 0.0 {
+            return 0.0;
+        }
+        // P(X < count), summed in log space so large lambdas don't underflow.
+        let ln_lambda = lambda.ln();
+        let mut ln_term = -lambda;
+        let mut below = 0.0;
+        for i in 0..count {
+            if i > 0 {
+                ln_term += ln_lambda - (i as f64).ln();
+            }
+            below += ln_term.exp();
+        }
+        (1.0 - below).clamp(0.0, 1.0)
+    }
+}
+
+/// Difficulty of a whole search, where every key is tested against several
+/// patterns and each pattern needs its own number of matches.
+#[derive(Debug, Clone)]
+pub struct SearchDifficulty {
+    targets: Vec<(Difficulty, u64)>,
+}
+
+impl SearchDifficulty {
+    /// Returns `None` if any pattern's difficulty is unknown.
+    pub fn new(difficulties: &[Option<Difficulty>], counts: &[u64]) -> Option<Self> {
+        let targets = difficulties
+            .iter()
+            .zip(counts)
+            .map(|(difficulty, &count)| Some(((*difficulty)?, count)))
+            .collect::<Option<_>>()?;
+        Some(SearchDifficulty { targets })
+    }
+
+    /// Keys needed for the hardest pattern to find its `remaining` matches.
+    /// The patterns share one key stream, so the others finish on the way.
+    pub fn expected_attempts(&self, remaining: &[u64]) -> f64 {
+        self.targets
+            .iter()
+            .zip(remaining)
+            .map(|(&(difficulty, _), &count)| difficulty.expected_attempts(count))
+            .fold(0.0, f64::max)
+    }
+
+    /// Chance that every pattern has all of its matches after `attempts`.
+    pub fn success_probability(&self, attempts: f64) -> f64 {
+        self.targets
+            .iter()
+            .map(|&(difficulty, count)| difficulty.success_probability(count, attempts))
+            .product()
+    }
+
+    /// Number of keys needed for a `confidence` chance of finishing.
+    pub fn attempts_for_confidence(&self, confidence: f64) -> f64 {
+        let counts: Vec<u64> = self
+            .targets
+            .iter()
+            .map(|&(_, count)| count.max(1))
+            .collect();
+        let initial = self.expected_attempts(&counts);
+        if !initial.is_finite() {
+            return f64::INFINITY;
+        }
+        attempts_for_confidence(initial, confidence, |attempts| {
+            self.success_probability(attempts)
+        })
+    }
+
+    /// Expected time for every pattern to find its `remaining` matches at
+    /// `rate` keys per second.
+    pub fn eta(&self, remaining: &[u64], rate: f64) -> Option<Duration> {
+        if rate <= 0.0 {
+            return None;
+        }
+        Duration::try_from_secs_f64(self.expected_attempts(remaining) / rate).ok()
+    }
+}
+
+/// Bisects for the smallest number of attempts at which `success` reaches
+/// `confidence`, starting the search from `initial`.
+fn attempts_for_confidence(initial: f64, confidence: f64, success: impl Fn(f64) -> f64) -> f64 {
+    let mut low = 0.0;
+    let mut high = initial;
+    while success(high) < confidence {
+        high *= 2.0;
+    }
+    for _ in 0..64 {
+        let mid = (low + high) / 2.0;
+        if success(mid) < confidence {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    high
+}
+
+fn prefix_probability(pattern: &str, case_sensitive: bool) -> f64 {
+    let total: BigUint = base58::prefix_variants(pattern, case_sensitive)
+        .iter()
+        .flat_map(|prefix| base58::prefix_ranges(prefix))
+        .map(|(start, end)| end - start)
+        .sum();
+    let space = BigUint::from(1u8) << (8 * PUBKEY_BYTES);
+    // Both fit in an f64, whose exponent reaches well past 2^256, and each
+    // conversion rounds to the nearest value, so even a pattern matched by a
+    // handful of keys gets a tiny but non-zero probability.
+    let total = total.to_f64().unwrap_or(0.0);
+    let space = space.to_f64().unwrap_or(f64::INFINITY);
+    total / space
+}
+
+fn uniform_probability(pattern: &str, case_sensitive: bool) -> f64 {
+    pattern
+        .chars()
+        .map(|c| {
+            let spellings = base58::prefix_variants(&c.to_string(), case_sensitive).len();
+            spellings as f64 / base58::ALPHABET.len() as f64
+        })
+        .product()
+}
+
+/// Formats a count with thousands separators, e.g. `12,345,678`.
+pub fn format_count(value: f64) -> String {
+    if !value.is_finite() {
+        return "∞".to_string();
+    }
+    let digits = format!("{:.0}", value);
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Formats a duration coarsely, e.g. `3d 4h`, `12m 5s` or `800ms`.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs == 0 {
+        return format!("{}ms", duration.as_millis());
+    }
+    let (days, hours, minutes, seconds) =
+        (secs / 86_400, secs / 3_600 % 24, secs / 60 % 60, secs % 60);
+    if days >= 365 {
+        format!("{} years", format_count(days as f64 / 365.0))
+    } else if days > 0 {
+        format!("{}d {}h", days, hours)
+    } else if hours > 0 {
+        format!("{}h {}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(actual: f64, expected: f64) -> bool {
+        (actual / expected - 1.0).abs() < 1e-9
+    }
+
+    #[test]
+    fn one_character_prefixes_are_about_one_in_58() {
+        let total: f64 = base58::ALPHABET
+            .iter()
+            .map(|&c| prefix_probability(&(c as char).to_string(), true))
+            .sum();
+        // On average one in 58, as every key starts with some character.
+        assert!(close(total / 58.0, 1.0 / 58.0), "{}", total);
+        // But 44-character addresses can only start with `1` to `J`, so
+        // those are several times likelier than the letters after them.
+        let (early, late) = (prefix_probability("A", true), prefix_probability("z", true));
+        assert!(
+            early > 3.0 / 58.0 && late < 0.1 / 58.0,
+            "{} {}",
+            early,
+            late
+        );
+        assert!(close(prefix_probability("1", true), 1.0 / 256.0));
+    }
+
+    #[test]
+    fn rare_prefixes_keep_a_probability() {
+        // Eight zero bytes in a row: exactly one key in 2^64.
+        let p = prefix_probability("11111111", true);
+        assert!(close(p, 2f64.powi(-64)), "{}", p);
+        let p = prefix_probability(&"1".repeat(32), true);
+        assert!(close(p, 2f64.powi(-256)), "{}", p);
+        let difficulty = Difficulty::new("11111111", MatchPosition::Prefix, true);
+        assert!(difficulty.expected_attempts(1).is_finite());
+        assert!(difficulty.expected_attempts(1) > 1e19);
+    }
+
+    #[test]
+    fn suffixes_are_uniform() {
+        for k in 1..=9 {
+            let pattern = "z".repeat(k);
+            let p = Difficulty::new(&pattern, MatchPosition::Suffix, true).probability();
+            assert!(close(p, 58f64.powi(-(k as i32))), "{} {}", k, p);
+        }
+        // `a` and `A` are both in the alphabet, `o` has no `O`.
+        let p = Difficulty::new("ao", MatchPosition::Suffix, false).probability();
+        assert!(close(p, 2.0 / 58.0 / 58.0), "{}", p);
+    }
+}
@@ -0,0 +1,134 @@
+use curve25519_dalek::{EdwardsPoint, Scalar};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha512};
+use solana_sdk::{
+    bs58,
+    pubkey::Pubkey,
+    signature::{Signature, Signer, SignerError},
+};
+use zeroize::Zeroize;
+
+/// An ed25519 signing key held as its raw scalar rather than a seed.
+///
+/// Keys from a [`ScalarWalk`](crate::walk::ScalarWalk) have no seed, so
+/// they can't be written as a Solana keypair file or imported into a
+/// wallet. This is the "expanded" form ed25519 derives from a seed anyway,
+/// `scalar || nonce prefix`, and signs exactly like a normal keypair: it
+/// implements [`Signer`], so it can sign transactions through the SDK.
+///
+/// The scalar and prefix are wiped when the key is dropped.
+pub struct ExpandedKeypair {
+    scalar: Scalar,
+    prefix: [u8; 32],
+    public: Pubkey,
+}
+
+impl ExpandedKeypair {
+    /// Wraps `scalar` with a fresh random nonce prefix.
+    pub fn from_scalar(scalar: Scalar) -> Self {
+        let mut prefix = [0u8; 32];
+        OsRng.fill_bytes(&mut prefix);
+        ExpandedKeypair::new(scalar, prefix)
+    }
+
+    pub fn new(scalar: Scalar, prefix: [u8; 32]) -> Self {
+        let public = Pubkey::new_from_array(EdwardsPoint::mul_base(&scalar).compress().to_bytes());
+        ExpandedKeypair {
+            scalar,
+            prefix,
+            public,
+        }
+    }
+
+    /// The 64-byte `scalar || prefix` encoding.
+    pub fn to_bytes(&self) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[..32].copy_from_slice(self.scalar.as_bytes());
+        bytes[32..].copy_from_slice(&self.prefix);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let bytes: &[u8; 64] = bytes
+            .try_into()
+            .map_err(|_| format!("expanded key must be 64 bytes, got {}", bytes.len()))?;
+        let scalar = Option::from(Scalar::from_canonical_bytes(
+            bytes[..32].try_into().unwrap(),
+        ))
+        .ok_or("expanded key scalar is not reduced")?;
+        Ok(ExpandedKeypair::new(
+            scalar,
+            bytes[32..].try_into().unwrap(),
+        ))
+    }
+
+    pub fn to_base58_string(&self) -> String {
+        bs58::encode(self.to_bytes()).into_string()
+    }
+
+    pub fn from_base58_string(s: &str) -> Result<Self, String> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|e| format!("invalid base58: {}", e))?;
+        ExpandedKeypair::from_bytes(&bytes)
+    }
+
+    /// Signs `message` as RFC 8032 ed25519 does once the seed is expanded.
+    pub fn sign(&self, message: &[u8]) -> Signature {
+        let r = hash_to_scalar(&[&self.prefix, message]);
+        let big_r = EdwardsPoint::mul_base(&r).compress();
+        let k = hash_to_scalar(&[big_r.as_bytes(), self.public.as_ref(), message]);
+        let s = r + k * self.scalar;
+
+        let mut signature = [0u8; 64];
+        signature[..32].copy_from_slice(big_r.as_bytes());
+        signature[32..].copy_from_slice(s.as_bytes());
+        Signature::from(signature)
+    }
+}
+
+impl Drop for ExpandedKeypair {
+    fn drop(&mut self) {
+        self.scalar.zeroize();
+        self.prefix.zeroize();
+    }
+}
+
+impl Signer for ExpandedKeypair {
+    fn try_pubkey(&self) -> Result<Pubkey, SignerError> {
+        Ok(self.public)
+    }
+
+    fn try_sign_message(&self, message: &[u8]) -> Result<Signature, SignerError> {
+        Ok(self.sign(message))
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut hasher = Sha512::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    Scalar::from_bytes_mod_order_wide(&hasher.finalize().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signatures_verify_and_keys_round_trip() {
+        let key = ExpandedKeypair::from_scalar(Scalar::from(123_456_789u64));
+        let signature = key.sign(b"vanity");
+        assert!(signature.verify(key.pubkey().as_ref(), b"vanity"));
+        assert!(!signature.verify(key.pubkey().as_ref(), b"other"));
+
+        let restored = ExpandedKeypair::from_base58_string(&key.to_base58_string()).unwrap();
+        assert_eq!(restored.pubkey(), key.pubkey());
+        assert_eq!(restored.sign(b"vanity"), signature);
+    }
+}
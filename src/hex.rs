@@ -0,0 +1,32 @@
+//! Lowercase hex encoding, used for `hex:` seeds and split-key offsets.
+
+/// Encodes bytes as lowercase hex digits.
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Decodes hex digits of either case.
+pub fn parse_hex(s: &str) -> Result<Vec<u8>, String> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return Err("expected an even number of hex digits".to_string());
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_bytes() {
+        assert_eq!(hex(&[0, 0x0f, 0xff]), "000fff");
+        assert_eq!(parse_hex("000FfF"), Ok(vec![0, 0x0f, 0xff]));
+        assert_eq!(parse_hex(""), Ok(vec![]));
+        for bad in ["0", "0g", "éé"] {
+            assert!(parse_hex(bad).is_err(), "{}", bad);
+        }
+    }
+}
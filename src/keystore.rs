@@ -0,0 +1,290 @@
+//! Password-encrypted keystore files.
+//!
+//! Each file holds one secret key, encrypted with AES-256-GCM-SIV under a
+//! key derived from a password with [`scrypt`](crate::scrypt). The public
+//! key and key kind are stored in the clear so files can be told apart
+//! without the password, and are authenticated along with the ciphertext.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "pubkey": "<base58 address>",
+//!   "kind": "keypair",
+//!   "kdf": { "name": "scrypt", "log_n": 17, "r": 8, "p": 1, "salt": "<base58>" },
+//!   "cipher": { "name": "aes-256-gcm-siv", "nonce": "<base58>" },
+//!   "ciphertext": "<base58>"
+//! }
+//! ```
+
+use crate::{
+    expanded_key::ExpandedKeypair,
+    scrypt::{self, Params},
+    secret::SecretKey,
+};
+use aes_gcm_siv::{
+    aead::{Aead, NewAead, Payload},
+    Aes256GcmSiv, Nonce,
+};
+use rand::{rngs::OsRng, RngCore};
+use serde_json::{json, Value};
+use solana_sdk::{
+    bs58,
+    signature::{Keypair, Signer},
+};
+use std::fmt;
+use zeroize::Zeroizing;
+
+const VERSION: u64 = 1;
+const SALT_LEN: usize = 32;
+const NONCE_LEN: usize = 12;
+
+/// A password held in memory, kept out of `Debug` output and wiped when
+/// dropped.
+#[derive(Clone)]
+pub struct Password(Zeroizing<String>);
+
+impl Password {
+    pub fn new(password: String) -> Self {
+        Password(Zeroizing::new(password))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        self.0.as_bytes()
+    }
+}
+
+impl fmt::Debug for Password {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Password(..)")
+    }
+}
+
+/// What the 64 secret bytes in a keystore are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyKind {
+    /// A Solana keypair, `seed || public key`
+    Keypair,
+    /// An expanded ed25519 key, `scalar || nonce prefix`; see
+    /// [`ExpandedKeypair`]
+    Expanded,
+}
+
+impl KeyKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            KeyKind::Keypair => "keypair",
+            KeyKind::Expanded => "expanded",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "keypair" => Ok(KeyKind::Keypair),
+            "expanded" => Ok(KeyKind::Expanded),
+            _ => Err(format!("unknown key kind `{}`", s)),
+        }
+    }
+}
+
+/// A secret key recovered from a keystore.
+#[derive(Debug)]
+pub struct DecryptedKey {
+    pub public_key: String,
+    pub kind: KeyKind,
+    pub secret: SecretKey,
+}
+
+/// Encrypts the 64-byte `secret` of `public_key` into a keystore file's
+/// contents.
+pub fn encrypt(
+    public_key: &str,
+    kind: KeyKind,
+    secret: &[u8],
+    password: &Password,
+    params: Params,
+) -> String {
+    let mut salt = [0u8; SALT_LEN];
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut salt);
+    OsRng.fill_bytes(&mut nonce);
+
+    let cipher = cipher(password, &salt, params);
+    let ciphertext = cipher
+        .encrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: secret,
+                aad: &associated_data(public_key, kind),
+            },
+        )
+        .expect("AES-GCM-SIV encrypts any message this small");
+
+    let keystore = json!({
+        "version": VERSION,
+        "pubkey": public_key,
+        "kind": kind.as_str(),
+        "kdf": {
+            "name": "scrypt",
+            "log_n": params.log_n,
+            "r": params.r,
+            "p": params.p,
+            "salt": bs58::encode(salt).into_string(),
+        },
+        "cipher": {
+            "name": "aes-256-gcm-siv",
+            "nonce": bs58::encode(nonce).into_string(),
+        },
+        "ciphertext": bs58::encode(ciphertext).into_string(),
+    });
+    serde_json::to_string_pretty(&keystore).expect("JSON values always serialize")
+}
+
+/// Decrypts a keystore file's contents, checking that the secret key
+/// really belongs to the public key stored alongside it.
+pub fn decrypt(contents: &str, password: &Password) -> Result<DecryptedKey, String> {
+    let keystore: Value =
+        serde_json::from_str(contents).map_err(|e| format!("not a keystore: {}", e))?;
+    if keystore["version"].as_u64() != Some(VERSION) {
+        return Err(format!(
+            "unsupported keystore version {}",
+            keystore["version"]
+        ));
+    }
+    let public_key = string_field(&keystore["pubkey"], "pubkey")?;
+    let kind = KeyKind::parse(string_field(&keystore["kind"], "kind")?)?;
+
+    let kdf = &keystore["kdf"];
+    if kdf["name"] != "scrypt" {
+        return Err(format!(
+            "unsupported key derivation function {}",
+            kdf["name"]
+        ));
+    }
+    let params = Params {
+        log_n: number_field(&kdf["log_n"], "kdf.log_n")?,
+        r: number_field(&kdf["r"], "kdf.r")?,
+        p: number_field(&kdf["p"], "kdf.p")?,
+    };
+    params.validate()?;
+    let salt = base58_field(&kdf["salt"], "kdf.salt")?;
+
+    let cipher_params = &keystore["cipher"];
+    if cipher_params["name"] != "aes-256-gcm-siv" {
+        return Err(format!("unsupported cipher {}", cipher_params["name"]));
+    }
+    let nonce: [u8; NONCE_LEN] = base58_field(&cipher_params["nonce"], "cipher.nonce")?
+        .try_into()
+        .map_err(|nonce: Vec<u8>| {
+            format!("nonce must be {} bytes, got {}", NONCE_LEN, nonce.len())
+        })?;
+    let ciphertext = base58_field(&keystore["ciphertext"], "ciphertext")?;
+
+    let secret = cipher(password, &salt, params)
+        .decrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &associated_data(public_key, kind),
+            },
+        )
+        .map(Zeroizing::new)
+        .map_err(|_| "wrong password or corrupted keystore".to_string())?;
+    let secret = SecretKey::from_slice(&secret)?;
+
+    let derived = match kind {
+        KeyKind::Keypair => Keypair::from_bytes(secret.as_bytes())
+            .map_err(|e| format!("invalid keypair: {}", e))?
+            .pubkey(),
+        KeyKind::Expanded => ExpandedKeypair::from_bytes(secret.as_bytes())?.pubkey(),
+    };
+    if derived.to_string() != public_key {
+        return Err(format!(
+            "secret key belongs to {}, not {}",
+            derived, public_key
+        ));
+    }
+    Ok(DecryptedKey {
+        public_key: public_key.to_owned(),
+        kind,
+        secret,
+    })
+}
+
+fn cipher(password: &Password, salt: &[u8], params: Params) -> Aes256GcmSiv {
+    let mut key = Zeroizing::new([0u8; 32]);
+    scrypt::scrypt(password.as_bytes(), salt, params, &mut *key);
+    // Keyed straight from the buffer that gets wiped, without a copy.
+    Aes256GcmSiv::new_from_slice(&*key).expect("AES-256 takes a 32-byte key")
+}
+
+/// Binds the cleartext fields to the ciphertext, so they can't be swapped
+/// between files.
+fn associated_data(public_key: &str, kind: KeyKind) -> Vec<u8> {
+    format!(
+        "vanaddy-keystore-v{}:{}:{}",
+        VERSION,
+        public_key,
+        kind.as_str()
+    )
+    .into_bytes()
+}
+
+fn string_field<'a>(value: &'a Value, name: &str) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("keystore field `{}` is missing or not a string", name))
+}
+
+fn number_field<T: TryFrom<u64>>(value: &Value, name: &str) -> Result<T, String> {
+    value
+        .as_u64()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| format!("keystore field `{}` is missing or out of range", name))
+}
+
+fn base58_field(value: &Value, name: &str) -> Result<Vec<u8>, String> {
+    bs58::decode(string_field(value, name)?)
+        .into_vec()
+        .map_err(|e| format!("keystore field `{}` is not base58: {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: Params = Params {
+        log_n: 4,
+        r: 8,
+        p: 1,
+    };
+
+    #[test]
+    fn keystores_round_trip_and_reject_tampering() {
+        let keypair = Keypair::new();
+        let public_key = keypair.pubkey().to_string();
+        let password = Password::new("correct horse".to_string());
+        let contents = encrypt(
+            &public_key,
+            KeyKind::Keypair,
+            &keypair.to_bytes(),
+            &password,
+            FAST,
+        );
+
+        let key = decrypt(&contents, &password).unwrap();
+        assert_eq!(key.public_key, public_key);
+        assert_eq!(key.kind, KeyKind::Keypair);
+        assert_eq!(key.secret.as_bytes(), &keypair.to_bytes());
+
+        let wrong = Password::new("battery staple".to_string());
+        assert!(decrypt(&contents, &wrong).is_err());
+
+        let other = Keypair::new().pubkey().to_string();
+        let swapped = contents.replace(&public_key, &other);
+        assert!(decrypt(&swapped, &password).is_err());
+    }
+}
@@ -0,0 +1,23 @@
+pub mod automaton;
+pub mod base58;
+pub mod cli;
+pub mod distributed;
+pub mod estimate;
+pub mod expanded_key;
+pub mod hex;
+pub mod keystore;
+pub mod matcher;
+pub mod mnemonic;
+pub mod output;
+pub mod pda;
+pub mod prefilter;
+pub mod regex_pattern;
+pub mod scrypt;
+pub mod seal;
+pub mod search;
+pub mod secret;
+pub mod server;
+pub mod split_key;
+pub mod state;
+pub mod walk;
+pub mod with_seed;
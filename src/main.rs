@@ -1,61 +1,237 @@
-use csv::WriterBuilder;
-use rayon::prelude::*;
-use solana_sdk::{
-    bs58,
-    signature::{Keypair, Signer},
-};
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
+use solana_sdk::signature::Signer;
 use std::{
-    fs::File,
-    io::{self, BufWriter, Write},
-    sync::{
-        atomic::{AtomicBool, Ordering},
-        mpsc, Arc, Mutex,
-    },
+    env, fs,
+    io::{self, Write},
+    net::TcpListener,
+    process::{self, ExitCode},
+    sync::{mpsc, Arc},
     thread,
     time::{Duration, Instant},
 };
+use vanaddy::{
+    cli::{
+        self, Cli, Command, ExportArgs, KeygenArgs, ServeArgs, SplitKeyCombineArgs,
+        SplitKeyCommand, SplitKeyNewArgs, UnsealArgs, WorkerArgs,
+    },
+    distributed::{self, Job},
+    estimate::{self, Difficulty, SearchDifficulty},
+    keystore::{self, KeyKind, Password},
+    matcher::MatchPosition,
+    output::{self, Output, WriteMode},
+    scrypt::Params,
+    seal::{self, Identity},
+    search::{self, Generator, SearchConfig},
+    secret::{self, SecretKey},
+    server,
+    split_key::SplitSecret,
+    state::SearchState,
+};
+use zeroize::Zeroizing;
 
-const BATCH_SIZE: usize = 1000;
+/// Exit status when the time limit ran out before all wallets were found.
+const EXIT_INCOMPLETE: u8 = 3;
 
-fn main() -> io::Result<()> {
-    display_banner();
-    let vanity_string = read_vanity_string()?;
-    let case_sensitive = read_case_sensitivity()?;
-    let wallet_count_target = read_wallet_count_target()?;
-    let max_threads = read_thread_count()?;
-    let csv_file_path = "vanity_wallets.csv".to_string();
+/// Exit status when the search was stopped by SIGINT or SIGTERM, following
+/// the shell's `128 + SIGINT` convention.
+const EXIT_INTERRUPTED: u8 = 130;
+
+/// Environment variable holding the keystore password, for runs where
+/// nobody can type it.
+const PASSWORD_VAR: &str = "VANADDY_PASSWORD";
+
+/// Environment variable holding the BIP39 passphrase of a mnemonic search.
+const PASSPHRASE_VAR: &str = "VANADDY_PASSPHRASE";
+
+/// How long `--estimate` runs the workers to measure throughput.
+const BENCHMARK_DURATION: Duration = Duration::from_secs(3);
 
-    prepare_csv_file(&csv_file_path)?;
+fn main() -> ExitCode {
+    // With no arguments at all, fall back to the interactive prompts.
+    let config = if env::args_os().len() <= 1 {
+        display_banner();
+        match read_config() {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                return ExitCode::from(2);
+            }
+        }
+    } else {
+        let cli = Cli::parse();
+        if let Some(command) = &cli.command {
+            let result = match command {
+                Command::Export(args) => export(args),
+                Command::Keygen(args) => keygen(args),
+                Command::Unseal(args) => unseal(args),
+                Command::Worker(args) => worker(args),
+                Command::SplitKey(SplitKeyCommand::New(args)) => split_key_new(args),
+                Command::SplitKey(SplitKeyCommand::Combine(args)) => split_key_combine(args),
+                Command::Serve(args) => serve(args),
+            };
+            return match result {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+        let estimate_only = cli.estimate;
+        let config = match cli.into_config() {
+            Ok(config) => config,
+            Err(message) => Cli::command()
+                .error(ErrorKind::ValueValidation, message)
+                .exit(),
+        };
+        if estimate_only {
+            print_estimate(&config);
+            return ExitCode::SUCCESS;
+        }
+        config
+    };
 
-    let found_count = Arc::new(Mutex::new(0u64));
-    let wallet_count = Arc::new(Mutex::new(0));
+    match run(&config) {
+        Ok(Outcome::Complete) => ExitCode::SUCCESS,
+        Ok(Outcome::Incomplete) => ExitCode::from(EXIT_INCOMPLETE),
+        Ok(Outcome::Interrupted) => ExitCode::from(EXIT_INTERRUPTED),
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// How a search ended.
+enum Outcome {
+    Complete,
+    Incomplete,
+    Interrupted,
+}
+
+/// Runs a search to completion and reports how it ended.
+fn run(config: &SearchConfig) -> io::Result<Outcome> {
+    let wallet_count_target = config.wallet_count_target();
+    let patterns = config.patterns();
+    let targets = config.counts();
+    let difficulties = Difficulty::for_patterns(&patterns);
+    let difficulty = SearchDifficulty::new(&difficulties, &targets);
+    if config.lock_memory {
+        secret::lock_memory()?;
+    }
+    let password = config
+        .outputs
+        .iter()
+        .any(|output| matches!(output, Output::KeystoreDir(_)))
+        .then(|| read_password(true))
+        .transpose()?;
+    let passphrase = (config.generator == Generator::Mnemonic && config.passphrase)
+        .then(read_passphrase)
+        .transpose()?;
+    let sinks = output::open_sinks(
+        &config.outputs,
+        config.write_mode,
+        &config.mode(),
+        config.generator,
+        password.as_ref(),
+    )?;
+    let mut status = status_stream(config);
+    for (target, pattern_difficulty) in config.targets.iter().zip(&difficulties) {
+        match pattern_difficulty {
+            Some(pattern_difficulty) => writeln!(
+                status,
+                "Difficulty of `{}`: 1 in {} keys per match, about {} keys for {} wallet(s)",
+                target.pattern,
+                estimate::format_count(1.0 / pattern_difficulty.probability()),
+                estimate::format_count(pattern_difficulty.expected_attempts(target.count)),
+                target.count
+            )?,
+            None => writeln!(status, "Difficulty of `{}`: unknown", target.pattern)?,
+        }
+    }
+
+    let state = Arc::new(SearchState::new(targets, config.max_threads));
+    let start_time = Instant::now();
+
+    // On SIGINT or SIGTERM, let the workers finish their batch so every
+    // match already found reaches the results file. A second signal quits
+    // at once.
+    {
+        let state = state.clone();
+        ctrlc::set_handler(move || {
+            if state.was_interrupted() {
+                process::exit(EXIT_INTERRUPTED.into());
+            }
+            state.interrupt();
+        })
+        .map_err(io::Error::other)?;
+    }
 
     let (tx, rx) = mpsc::channel();
-    let handles = spawn_threads(
-        max_threads,
-        vanity_string,
-        case_sensitive,
-        found_count.clone(),
-        wallet_count_target,
-        wallet_count.clone(),
-        tx,
-    );
+    let coordinator = match config.listen {
+        Some(address) => {
+            let listener = TcpListener::bind(address)?;
+            writeln!(
+                status,
+                "Listening for workers on {}",
+                listener.local_addr()?
+            )?;
+            Some(distributed::serve(
+                listener,
+                Job::new(config),
+                config.patterns(),
+                state.clone(),
+                tx.clone(),
+            )?)
+        }
+        None => None,
+    };
+    let handles = search::spawn_threads(config.key_source(passphrase), patterns, state.clone(), tx);
 
-    let writer_handle = start_csv_writer_thread(rx, csv_file_path);
+    let writer_handle = output::start_writer_thread(rx, sinks);
 
     // Periodically print the count of generated wallets
     let counter_handle = {
-        let wallet_count = wallet_count.clone();
-        let found_count = found_count.clone();
+        let state = state.clone();
+        let time_limit = config.time_limit;
+        let mnemonic = config.generator == Generator::Mnemonic;
+        let mut status = status_stream(config);
         thread::spawn(move || {
-            while *found_count.lock().unwrap() < wallet_count_target {
-                print!(
-                    "\rWallets generated: {} | Found: {}/{}",
-                    wallet_count.lock().unwrap(),
-                    found_count.lock().unwrap(),
-                    wallet_count_target
+            while !state.is_finished() {
+                if time_limit.is_some_and(|limit| start_time.elapsed() >= limit) {
+                    state.stop();
+                    break;
+                }
+                let generated = state.generated();
+                let found = state.found();
+                let remaining = state.remaining();
+                let elapsed = start_time.elapsed().as_secs_f64();
+                let rate = generated as f64 / elapsed;
+                let eta = difficulty
+                    .as_ref()
+                    .and_then(|difficulty| difficulty.eta(&remaining, rate))
+                    .map_or_else(|| "-".to_string(), estimate::format_duration);
+                let seeds = if mnemonic {
+                    let seeds = state.seeds();
+                    format!(
+                        "Seed phrases: {} ({}/s) | ",
+                        seeds,
+                        estimate::format_count(seeds as f64 / elapsed)
+                    )
+                } else {
+                    String::new()
+                };
+                let _ = write!(
+                    status,
+                    "\r{}Wallets generated: {} | Found: {}/{} | {}/s | ETA {}    ",
+                    seeds,
+                    generated,
+                    found.iter().sum::<u64>(),
+                    wallet_count_target,
+                    estimate::format_count(rate),
+                    eta
                 );
-                io::stdout().flush().unwrap();
+                let _ = status.flush();
                 thread::sleep(Duration::from_millis(25));
             }
         })
@@ -64,19 +240,347 @@ fn main() -> io::Result<()> {
     for handle in handles {
         let _ = handle.join();
     }
+    if let Some(coordinator) = coordinator {
+        let _ = coordinator.join();
+    }
 
-    let _ = writer_handle.join();
     let _ = counter_handle.join();
-    report_completion(
-        &found_count,
-        &wallet_count,
-        wallet_count_target,
-        Instant::now(),
+    output::finish_search(writer_handle, &state, config, start_time, &mut status)?;
+
+    Ok(if state.all_found() {
+        Outcome::Complete
+    } else if state.was_interrupted() {
+        Outcome::Interrupted
+    } else {
+        Outcome::Incomplete
+    })
+}
+
+/// Decrypts a keystore and prints or saves its secret key.
+fn export(args: &ExportArgs) -> io::Result<()> {
+    let contents = fs::read_to_string(&args.keystore)?;
+    let password = read_password(false)?;
+    let key = keystore::decrypt(&contents, &password)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+    let secret = if args.base58 {
+        key.secret.to_base58()
+    } else if key.kind == KeyKind::Expanded {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "this is an expanded key, which has no keypair file form; use --base58",
+        ));
+    } else {
+        key.secret.to_keypair_json()
+    };
+    match &args.output {
+        Some(path) => {
+            let mut file = output::create_private_file(path)?;
+            writeln!(file, "{}", secret.as_str())?;
+            file.sync_all()?;
+            eprintln!(
+                "Secret key of {} written to {}",
+                key.public_key,
+                path.display()
+            );
+        }
+        None => println!("{}", secret.as_str()),
+    }
+    Ok(())
+}
+
+/// Creates an identity for sealing results and prints its public key.
+fn keygen(args: &KeygenArgs) -> io::Result<()> {
+    let identity = Identity::generate();
+    let contents = Zeroizing::new(format!(
+        "# public key: {}\n{}\n",
+        identity.recipient(),
+        identity
+    ));
+    match &args.output {
+        Some(path) => {
+            let mut file = output::create_private_file(path)?;
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()?;
+            eprintln!("Identity written to {}", path.display());
+        }
+        None => print!("{}", contents.as_str()),
+    }
+    eprintln!("Public key: {}", identity.recipient());
+    Ok(())
+}
+
+/// Decrypts every record of a sealed results file.
+fn unseal(args: &UnsealArgs) -> io::Result<()> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let identity = Identity::from_file_contents(&fs::read_to_string(&args.identity)?)
+        .map_err(|e| invalid(format!("{}: {}", args.identity.display(), e)))?;
+    let contents = fs::read_to_string(&args.sealed)?;
+
+    let mut stdout = io::stdout().lock();
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (_, plaintext) = seal::open(line, &identity).map_err(|e| {
+            invalid(format!(
+                "{} line {}: {}",
+                args.sealed.display(),
+                number + 1,
+                e
+            ))
+        })?;
+        stdout.write_all(&plaintext)?;
+        writeln!(stdout)?;
+    }
+    Ok(())
+}
+
+/// Searches for a coordinator until it has every match.
+fn worker(args: &WorkerArgs) -> io::Result<()> {
+    let threads = args
+        .threads
+        .map_or_else(cli::default_thread_count, |threads| threads as usize);
+    eprintln!("Searching for {} on {} threads", args.coordinator, threads);
+    let generated = distributed::run_worker(args.coordinator.as_str(), threads)?;
+    eprintln!(
+        "The coordinator ended the search; {} wallets generated here",
+        generated
+    );
+    Ok(())
+}
+
+/// Runs the job API until the process is killed.
+fn serve(args: &ServeArgs) -> io::Result<()> {
+    let threads = args
+        .threads
+        .map_or_else(cli::default_thread_count, |threads| threads as usize);
+    let listener = TcpListener::bind(args.listen)?;
+    eprintln!(
+        "Serving jobs on http://{} with {} threads",
+        listener.local_addr()?,
+        threads
     );
+    server::serve(listener, threads, args.schedule)
+}
 
+/// Creates a split-key secret and prints the partial key for searchers.
+fn split_key_new(args: &SplitKeyNewArgs) -> io::Result<()> {
+    let secret = SplitSecret::generate();
+    let contents = Zeroizing::new(format!(
+        "# partial key: {}\n{}\n",
+        secret.partial_key(),
+        secret
+    ));
+    match &args.output {
+        Some(path) => {
+            let mut file = output::create_private_file(path)?;
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()?;
+            eprintln!("Split-key secret written to {}", path.display());
+        }
+        None => print!("{}", contents.as_str()),
+    }
+    eprintln!("Partial key: {}", secret.partial_key());
     Ok(())
 }
 
+/// Combines a split-key secret with a searcher's offset into the key of
+/// the vanity address, and prints, saves or encrypts it.
+fn split_key_combine(args: &SplitKeyCombineArgs) -> io::Result<()> {
+    let invalid = |e: String| io::Error::new(io::ErrorKind::InvalidData, e);
+    let contents = Zeroizing::new(fs::read_to_string(&args.secret)?);
+    let secret = SplitSecret::from_file_contents(&contents)
+        .map_err(|e| invalid(format!("{}: {}", args.secret.display(), e)))?;
+    let key = secret.combine(&args.offset);
+    let address = key.pubkey();
+    if let Some(expected) = args.address {
+        if address != expected {
+            return Err(invalid(format!(
+                "the offset gives {}, not {}; was it found for this secret's partial key?",
+                address, expected
+            )));
+        }
+    }
+
+    let secret_key = SecretKey::from_expanded(&key);
+    if let Some(path) = &args.keystore {
+        let password = read_password(true)?;
+        let contents = keystore::encrypt(
+            &address.to_string(),
+            KeyKind::Expanded,
+            secret_key.as_bytes(),
+            &password,
+            Params::RECOMMENDED,
+        );
+        let mut file = output::create_private_file(path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        eprintln!("Key of {} encrypted to {}", address, path.display());
+        return Ok(());
+    }
+    let encoded = secret_key.to_base58();
+    match &args.output {
+        Some(path) => {
+            let mut file = output::create_private_file(path)?;
+            writeln!(file, "{}", encoded.as_str())?;
+            file.sync_all()?;
+            eprintln!("Expanded key of {} written to {}", address, path.display());
+        }
+        None => {
+            println!("{}", encoded.as_str());
+            eprintln!("Address: {}", address);
+        }
+    }
+    Ok(())
+}
+
+/// Reads the keystore password from `VANADDY_PASSWORD`, or asks for it,
+/// twice if `confirm` is set.
+fn read_password(confirm: bool) -> io::Result<Password> {
+    if let Ok(password) = env::var(PASSWORD_VAR) {
+        return Ok(Password::new(password));
+    }
+    let password = rpassword::prompt_password("Keystore password: ")?;
+    if confirm {
+        if password.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the keystore password can't be empty",
+            ));
+        }
+        let repeated = Zeroizing::new(rpassword::prompt_password("Repeat password: ")?);
+        if *repeated != password {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "passwords don't match",
+            ));
+        }
+    }
+    Ok(Password::new(password))
+}
+
+/// Reads the BIP39 passphrase from `VANADDY_PASSPHRASE`, or asks for it
+/// twice; a typo would give keys no wallet can restore.
+fn read_passphrase() -> io::Result<Password> {
+    if let Ok(passphrase) = env::var(PASSPHRASE_VAR) {
+        return Ok(Password::new(passphrase));
+    }
+    let passphrase = Password::new(rpassword::prompt_password("BIP39 passphrase: ")?);
+    let repeated = Zeroizing::new(rpassword::prompt_password("Repeat passphrase: ")?);
+    if passphrase.as_str() != *repeated {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "passphrases don't match",
+        ));
+    }
+    Ok(passphrase)
+}
+
+fn print_estimate(config: &SearchConfig) {
+    let patterns = config.patterns();
+    let counts = config.counts();
+    let difficulties = Difficulty::for_patterns(&patterns);
+    let difficulty = SearchDifficulty::new(&difficulties, &counts);
+
+    println!(
+        "Matching {} ({})",
+        config.mode(),
+        if config.case_sensitive {
+            "case-sensitive"
+        } else {
+            "case-insensitive"
+        }
+    );
+    for (target, pattern_difficulty) in config.targets.iter().zip(&difficulties) {
+        match pattern_difficulty {
+            Some(pattern_difficulty) => println!(
+                "Pattern `{}`: 1 in {} keys per match, about {} attempts for {} wallet(s)",
+                target.pattern,
+                estimate::format_count(1.0 / pattern_difficulty.probability()),
+                estimate::format_count(pattern_difficulty.expected_attempts(target.count)),
+                target.count
+            ),
+            None => println!(
+                "Pattern `{}`: too complex to estimate, only throughput will be measured",
+                target.pattern
+            ),
+        }
+    }
+    if let Some(difficulty) = &difficulty {
+        println!(
+            "Expected attempts for all {} wallet(s): {}",
+            config.wallet_count_target(),
+            estimate::format_count(difficulty.expected_attempts(&counts))
+        );
+    }
+
+    println!(
+        "Benchmarking {} thread(s) for {}...",
+        config.max_threads,
+        estimate::format_duration(BENCHMARK_DURATION)
+    );
+    let throughput = search::measure_throughput(
+        config.key_source(None),
+        config.max_threads,
+        patterns,
+        BENCHMARK_DURATION,
+    );
+    let rate = throughput.keys;
+    println!("Throughput: {} keys/s", estimate::format_count(rate));
+    if config.generator == Generator::Mnemonic {
+        println!(
+            "Seed phrases: {}/s, {} account(s) each",
+            estimate::format_count(throughput.seeds),
+            u64::from(config.accounts.end() - config.accounts.start()) + 1
+        );
+    }
+    let Some(difficulty) = difficulty else {
+        return;
+    };
+    if let Some(eta) = difficulty.eta(&counts, rate) {
+        println!("Expected time: {}", estimate::format_duration(eta));
+    }
+    for confidence in [0.5, 0.9, 0.99] {
+        let attempts = difficulty.attempts_for_confidence(confidence);
+        let time = Duration::try_from_secs_f64(attempts / rate)
+            .map_or_else(|_| "-".to_string(), estimate::format_duration);
+        println!(
+            "{:>3}% chance after {} attempts ({})",
+            confidence * 100.0,
+            estimate::format_count(attempts),
+            time
+        );
+    }
+}
+
+fn read_config() -> io::Result<SearchConfig> {
+    let vanity_strings = read_vanity_strings()?;
+    let case_sensitive = read_case_sensitivity()?;
+    let position = read_match_position()?;
+    let wallet_count_target = read_wallet_count_target()?;
+    let max_threads = read_thread_count()?;
+
+    let targets = vanity_strings
+        .iter()
+        .map(|spec| cli::parse_target(spec, wallet_count_target))
+        .collect::<Result<_, _>>()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let config = SearchConfig {
+        case_sensitive,
+        position,
+        max_threads,
+        outputs: vec![Output::Csv("vanity_wallets.csv".into())],
+        // Keep the results of earlier interactive runs
+        write_mode: WriteMode::Append,
+        ..SearchConfig::new(targets, Generator::Keypair)
+    };
+    config
+        .validate()
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    Ok(config)
+}
+
 fn display_banner() {
     println!("██╗   ██╗ █████╗ ███╗   ██╗ █████╗ ██████╗ ██████╗ ██╗   ██╗");
     println!("██║   ██║██╔══██╗████╗  ██║██╔══██╗██╔══██╗██╔══██╗╚██╗ ██╔╝");
@@ -87,11 +591,16 @@ fn display_banner() {
     println!("==========================================================\n");
 }
 
-fn read_vanity_string() -> io::Result<String> {
-    println!("Enter a vanity string (1-9 characters): ");
-    let mut vanity_string = String::new();
-    io::stdin().read_line(&mut vanity_string)?;
-    Ok(vanity_string.trim().to_owned())
+fn read_vanity_strings() -> io::Result<Vec<String>> {
+    println!("Enter one or more vanity strings (1-9 characters, comma-separated): ");
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(input
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_owned)
+        .collect())
 }
 
 fn read_case_sensitivity() -> io::Result<bool> {
@@ -101,18 +610,34 @@ fn read_case_sensitivity() -> io::Result<bool> {
     Ok(answer.trim().eq_ignore_ascii_case("yes"))
 }
 
+fn read_match_position() -> io::Result<MatchPosition> {
+    println!("Where should the vanity string appear? (prefix/suffix/both/anywhere): ");
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    let answer = answer.trim();
+    if answer.is_empty() {
+        return Ok(MatchPosition::Prefix);
+    }
+    MatchPosition::from_str(answer, true)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+}
+
 fn read_thread_count() -> io::Result<usize> {
     println!("Enter the number of threads to use: ");
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
-    input
-        .trim()
-        .parse::<usize>()
-        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
+    match input.trim().parse::<usize>() {
+        Ok(0) => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "thread count must be at least 1",
+        )),
+        Ok(threads) => Ok(threads),
+        Err(e) => Err(io::Error::new(io::ErrorKind::InvalidInput, e)),
+    }
 }
 
 fn read_wallet_count_target() -> io::Result<u64> {
-    println!("Enter the number of wallets to find: ");
+    println!("Enter the number of wallets to find per vanity string: ");
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
     input
@@ -121,145 +646,12 @@ fn read_wallet_count_target() -> io::Result<u64> {
         .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))
 }
 
-fn prepare_csv_file(path: &str) -> io::Result<()> {
-    let file = File::create(path)?;
-    let mut writer = BufWriter::new(file);
-    writeln!(writer, "Public Key,Private Key,Note")?;
-    Ok(())
-}
-
-fn spawn_threads(
-    max_threads: usize,
-    vanity_string: String,
-    case_sensitive: bool,
-    found_count: Arc<Mutex<u64>>,
-    wallet_count_target: u64,
-    wallet_count: Arc<Mutex<u64>>,
-    tx: mpsc::Sender<(String, String)>,
-) -> Vec<thread::JoinHandle<()>> {
-    let vanity_lower = vanity_string.to_lowercase();
-
-    (0..max_threads)
-        .map(|_| {
-            let vanity_string = vanity_string.clone();
-            let vanity_lower = vanity_lower.clone();
-            let found_count = Arc::clone(&found_count);
-            let wallet_count = Arc::clone(&wallet_count);
-            let tx = tx.clone();
-
-            thread::spawn(move || {
-                let mut batch_keypairs: Vec<(String, String)> = Vec::with_capacity(BATCH_SIZE);
-
-                while *found_count.lock().unwrap() < wallet_count_target {
-                    // Generate keypairs in batches
-                    batch_keypairs.clear();
-                    (0..BATCH_SIZE).for_each(|_| {
-                        let keypair = Keypair::new();
-                        let public_key = keypair.pubkey().to_string();
-                        let private_key = bs58::encode(keypair.to_bytes()).into_string();
-                        batch_keypairs.push((public_key, private_key));
-                    });
-
-                    // Process the batch
-                    let matches: Vec<(String, String)> = batch_keypairs
-                        .iter()
-                        .filter(|(public_key, _)| {
-                            check_vanity_string(
-                                public_key,
-                                &vanity_string,
-                                &vanity_lower,
-                                case_sensitive,
-                            )
-                        })
-                        .cloned()
-                        .collect();
-
-                    // Update counts
-                    {
-                        let mut count = wallet_count.lock().unwrap();
-                        *count += BATCH_SIZE as u64;
-                    }
-
-                    // Send matches
-                    for keypair in matches {
-                        tx.send(keypair).unwrap();
-                        let mut found = found_count.lock().unwrap();
-                        *found += 1;
-                        if *found >= wallet_count_target {
-                            break;
-                        }
-                    }
-                }
-            })
-        })
-        .collect()
-}
-
-fn check_vanity_string(
-    public_key: &str,
-    vanity_string: &str,
-    vanity_lower: &str,
-    case_sensitive: bool,
-) -> bool {
-    if case_sensitive {
-        public_key.ends_with(vanity_string)
+/// Where progress and summaries go: standard error when the matches
+/// themselves are written to standard output, so they can be piped.
+fn status_stream(config: &SearchConfig) -> Box<dyn Write + Send> {
+    if config.outputs.contains(&Output::Stdout) {
+        Box::new(io::stderr())
     } else {
-        // Avoid allocating new strings for each comparison
-        public_key.to_lowercase().ends_with(vanity_lower)
-    }
-}
-
-fn start_csv_writer_thread(
-    rx: mpsc::Receiver<(String, String)>,
-    csv_file_path: String,
-) -> thread::JoinHandle<()> {
-    thread::spawn(move || {
-        let file = File::create(&csv_file_path).unwrap();
-        let buf_writer = BufWriter::with_capacity(32 * 1024, file); // Increased buffer size
-        let mut wtr = WriterBuilder::new().from_writer(buf_writer);
-
-        let mut batch = Vec::with_capacity(100);
-        while let Ok((public_key, private_key)) = rx.recv() {
-            batch.push(vec![
-                public_key,
-                private_key,
-                "Generated by Vanity".to_string(),
-            ]);
-
-            if batch.len() >= 100 {
-                for record in &batch {
-                    wtr.write_record(record).unwrap();
-                }
-                wtr.flush().unwrap();
-                batch.clear();
-            }
-        }
-
-        // Write remaining records
-        for record in &batch {
-            wtr.write_record(record).unwrap();
-        }
-        wtr.flush().unwrap();
-    })
-}
-
-fn report_completion(
-    found_count: &Arc<Mutex<u64>>,
-    wallet_count: &Arc<Mutex<u64>>,
-    wallet_count_target: u64,
-    start_time: Instant,
-) {
-    let found = *found_count.lock().unwrap();
-    if found >= wallet_count_target {
-        println!("\nFound all {} vanity addresses!", wallet_count_target);
-    } else {
-        println!(
-            "\nFound {} out of {} vanity addresses.",
-            found, wallet_count_target
-        );
+        Box::new(io::stdout())
     }
-    let count = *wallet_count.lock().unwrap();
-    println!("Total wallets generated: {}", count);
-    println!("Elapsed time: {:?}", start_time.elapsed());
-    println!("Results have been saved to vanity_wallets.csv");
 }
@@ -0,0 +1,221 @@
+use crate::{
+    automaton::PatternAutomaton, base58::PUBKEY_BYTES, prefilter::KeyFilter, regex_pattern,
+};
+use clap::ValueEnum;
+use regex::RegexSet;
+use std::{fmt, sync::Arc};
+
+/// Where in the address the vanity string has to appear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MatchPosition {
+    /// At the start of the address
+    Prefix,
+    /// At the end of the address
+    Suffix,
+    /// At both the start and the end of the address
+    Both,
+    /// Anywhere in the address
+    Anywhere,
+}
+
+impl MatchPosition {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatchPosition::Prefix => "prefix",
+            MatchPosition::Suffix => "suffix",
+            MatchPosition::Both => "both",
+            MatchPosition::Anywhere => "anywhere",
+        }
+    }
+}
+
+impl fmt::Display for MatchPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// Tests base58 addresses against a vanity string at a fixed position.
+///
+/// Addresses are pure ASCII, so case-insensitive comparison is done byte by
+/// byte without allocating a lowercased copy of every candidate.
+#[derive(Debug, Clone)]
+pub struct Matcher {
+    pattern: String,
+    position: MatchPosition,
+    case_sensitive: bool,
+}
+
+impl Matcher {
+    pub fn new(pattern: &str, position: MatchPosition, case_sensitive: bool) -> Self {
+        Matcher {
+            pattern: pattern.to_owned(),
+            position,
+            case_sensitive,
+        }
+    }
+
+    pub fn is_match(&self, address: &str) -> bool {
+        let address = address.as_bytes();
+        let pattern = self.pattern.as_bytes();
+        if address.len() < pattern.len() {
+            return false;
+        }
+        let at_start = || self.eq(&address[..pattern.len()], pattern);
+        let at_end = || self.eq(&address[address.len() - pattern.len()..], pattern);
+
+        match self.position {
+            MatchPosition::Prefix => at_start(),
+            MatchPosition::Suffix => at_end(),
+            MatchPosition::Both => at_start() && at_end(),
+            MatchPosition::Anywhere => address
+                .windows(pattern.len())
+                .any(|window| self.eq(window, pattern)),
+        }
+    }
+
+    fn eq(&self, candidate: &[u8], pattern: &[u8]) -> bool {
+        if self.case_sensitive {
+            candidate == pattern
+        } else {
+            candidate.eq_ignore_ascii_case(pattern)
+        }
+    }
+}
+
+/// Tests each address against several vanity strings at once, so one key
+/// stream can serve every pattern in a search.
+///
+/// The compiled form is shared, so cloning a set for each worker thread is
+/// cheap.
+#[derive(Debug, Clone)]
+pub struct PatternSet {
+    patterns: Vec<String>,
+    position: Option<MatchPosition>,
+    case_sensitive: bool,
+    filter: Arc<KeyFilter>,
+    compiled: Arc<Compiled>,
+}
+
+#[derive(Debug)]
+enum Compiled {
+    Literal(Box<PatternAutomaton>),
+    Regex(RegexSet),
+}
+
+impl PatternSet {
+    pub fn new<S: AsRef<str>>(
+        patterns: &[S],
+        position: MatchPosition,
+        case_sensitive: bool,
+    ) -> Self {
+        PatternSet {
+            patterns: patterns.iter().map(|p| p.as_ref().to_owned()).collect(),
+            position: Some(position),
+            case_sensitive,
+            filter: Arc::new(KeyFilter::new(patterns, position, case_sensitive)),
+            compiled: Arc::new(Compiled::Literal(Box::new(PatternAutomaton::new(
+                patterns,
+                position,
+                case_sensitive,
+            )))),
+        }
+    }
+
+    /// Treats each pattern as a regular expression over the address.
+    pub fn regex<S: AsRef<str>>(patterns: &[S], case_sensitive: bool) -> Result<Self, String> {
+        let set = regex_pattern::compile_set(patterns, case_sensitive)?;
+        Ok(PatternSet {
+            patterns: patterns.iter().map(|p| p.as_ref().to_owned()).collect(),
+            position: None,
+            case_sensitive,
+            filter: Arc::new(KeyFilter::accept_all()),
+            compiled: Arc::new(Compiled::Regex(set)),
+        })
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    pub fn pattern(&self, index: usize) -> &str {
+        &self.patterns[index]
+    }
+
+    /// Where literal patterns have to appear, or `None` for regexes.
+    pub fn position(&self) -> Option<MatchPosition> {
+        self.position
+    }
+
+    pub fn case_sensitive(&self) -> bool {
+        self.case_sensitive
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// Cheap check on a raw public key. `false` means no pattern can match
+    /// its address; `true` means the address still has to be checked.
+    pub fn may_match(&self, public_key: &[u8; PUBKEY_BYTES]) -> bool {
+        self.filter.may_match(public_key)
+    }
+
+    /// Indexes of every pattern `address` matches, in the order the
+    /// patterns were given.
+    pub fn matches(&self, address: &str) -> Vec<usize> {
+        match &*self.compiled {
+            Compiled::Literal(automaton) => automaton.matches(address),
+            Compiled::Regex(set) => set.matches(address).into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ADDRESS: &str = "SoLxyzBNpQ7abcSol";
+
+    fn is_match(pattern: &str, position: MatchPosition, case_sensitive: bool) -> bool {
+        Matcher::new(pattern, position, case_sensitive).is_match(ADDRESS)
+    }
+
+    #[test]
+    fn positions_pick_where_the_pattern_goes() {
+        use MatchPosition::*;
+        assert!(is_match("SoL", Prefix, true));
+        assert!(!is_match("SoL", Suffix, true));
+        assert!(is_match("Sol", Suffix, true));
+        assert!(!is_match("Sol", Prefix, true));
+        assert!(is_match("BNp", Anywhere, true));
+        assert!(!is_match("BNp", Prefix, true));
+        assert!(!is_match("BNp", Both, true));
+
+        // `Both` needs the pattern at each end, not just somewhere.
+        assert!(!is_match("Sol", Both, true));
+        assert!(is_match("Sol", Both, false));
+        assert!(is_match("Sol", Anywhere, true));
+        assert!(Matcher::new("ab", Both, true).is_match("ab"));
+        assert!(Matcher::new("ab", Both, true).is_match("abxab"));
+        assert!(!Matcher::new("ab", Anywhere, true).is_match("a"));
+    }
+
+    #[test]
+    fn case_insensitive_matching_ignores_ascii_case() {
+        use MatchPosition::*;
+        assert!(is_match("sol", Prefix, false));
+        assert!(!is_match("sol", Prefix, true));
+        assert!(is_match("bnp", Anywhere, false));
+        assert!(is_match("SOL", Suffix, false));
+
+        let set = PatternSet::new(&["sol", "BNP", "xyz"], Anywhere, false);
+        assert_eq!(set.matches(ADDRESS), vec![0, 1, 2]);
+        let set = PatternSet::new(&["sol", "BNP", "xyz"], Anywhere, true);
+        assert_eq!(set.matches(ADDRESS), vec![2]);
+    }
+}
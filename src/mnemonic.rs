@@ -0,0 +1,313 @@
+//! BIP39 seed phrases and SLIP-0010 ed25519 key derivation.
+//!
+//! Wallets such as Phantom and Solflare turn a seed phrase into keys the
+//! same way: the phrase and an optional passphrase are stretched into a
+//! 64-byte seed with PBKDF2 (BIP39), and the key for each account is
+//! derived from the seed along a hardened path, `m/44'/501'/0'/0'` for the
+//! first one (SLIP-0010). A vanity address found this way can be restored
+//! from the phrase in any of them.
+//!
+//! Stretching the phrase is by far the expensive part: every further
+//! account, `m/44'/501'/i'/0'`, costs only a few HMACs and a base point
+//! multiplication on top of it, see [`AccountRoot`].
+
+use crate::keystore::Password;
+use bip39::{Language, Mnemonic, MnemonicType, Seed};
+use clap::ValueEnum;
+use hmac::{Hmac, Mac};
+use sha2::Sha512;
+use solana_sdk::signer::keypair::{keypair_from_seed, Keypair};
+use std::{fmt, ops::RangeInclusive, str::FromStr};
+use zeroize::Zeroizing;
+
+/// Set on an index to make it hardened; ed25519 only has hardened children.
+const HARDENED: u32 = 1 << 31;
+
+/// How long generated seed phrases are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum WordCount {
+    /// 128 bits of entropy
+    #[value(name = "12")]
+    Twelve,
+    /// 256 bits of entropy
+    #[value(name = "24")]
+    TwentyFour,
+}
+
+impl WordCount {
+    fn mnemonic_type(self) -> MnemonicType {
+        match self {
+            WordCount::Twelve => MnemonicType::Words12,
+            WordCount::TwentyFour => MnemonicType::Words24,
+        }
+    }
+}
+
+/// The purpose and coin type levels every Solana account path starts with.
+const SOLANA_PREFIX: [u32; 2] = [44, 501];
+
+/// A path of hardened child indexes, e.g. `m/44'/501'/0'/0'`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DerivationPath(Vec<u32>);
+
+impl DerivationPath {
+    /// The path wallets use for a Solana account: `m/44'/501'/account'/0'`.
+    pub fn solana(account: u32) -> Self {
+        DerivationPath([&SOLANA_PREFIX[..], &[account, 0]].concat())
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("m")?;
+        for index in &self.0 {
+            write!(f, "/{}'", index)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for DerivationPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(format!("derivation path `{}` must start with `m`", s));
+        }
+        parts
+            .map(|part| {
+                part.strip_suffix('\'')
+                    .ok_or_else(|| {
+                        format!("`{}` is not hardened; ed25519 needs `{}'`", part, part)
+                    })?
+                    .parse()
+                    .ok()
+                    .filter(|index| index & HARDENED == 0)
+                    .ok_or_else(|| format!("invalid derivation path index `{}`", part))
+            })
+            .collect::<Result<_, _>>()
+            .map(DerivationPath)
+    }
+}
+
+/// What mnemonic workers need to turn phrases into keys.
+#[derive(Debug, Clone)]
+pub struct PhraseSettings {
+    pub word_count: WordCount,
+    /// The BIP39 passphrase, empty for none.
+    pub passphrase: Password,
+    /// Accounts whose keys are checked for every phrase.
+    pub accounts: RangeInclusive<u32>,
+}
+
+/// The seed phrase behind a match and the path its key was derived along.
+/// The phrase is wiped when dropped and kept out of `Debug` output.
+#[derive(Clone)]
+pub struct SeedPhrase {
+    phrase: Zeroizing<String>,
+    pub path: DerivationPath,
+}
+
+impl SeedPhrase {
+    pub fn phrase(&self) -> &str {
+        &self.phrase
+    }
+}
+
+impl fmt::Debug for SeedPhrase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SeedPhrase")
+            .field("path", &self.path.to_string())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A fresh random seed phrase.
+pub fn generate(word_count: WordCount) -> Mnemonic {
+    Mnemonic::new(word_count.mnemonic_type(), Language::English)
+}
+
+/// Parses an English seed phrase, checking its checksum.
+pub fn parse(phrase: &str) -> Result<Mnemonic, String> {
+    Mnemonic::from_phrase(phrase, Language::English)
+        .map_err(|e| format!("invalid seed phrase: {}", e))
+}
+
+/// Derives the keypair at `path` from `mnemonic` and `passphrase`.
+pub fn derive_keypair(
+    mnemonic: &Mnemonic,
+    passphrase: &Password,
+    path: &DerivationPath,
+) -> Keypair {
+    let seed = Seed::new(mnemonic, passphrase.as_str());
+    node_keypair(&derive_node(seed.as_bytes(), &path.0))
+}
+
+/// The node at `m/44'/501'` of a seed phrase, shared by all its Solana
+/// accounts. Wiped when dropped.
+pub struct AccountRoot {
+    node: Zeroizing<[u8; 64]>,
+}
+
+impl AccountRoot {
+    /// Stretches `mnemonic` and `passphrase` into the seed; this is what
+    /// makes seed phrases slow.
+    pub fn new(mnemonic: &Mnemonic, passphrase: &Password) -> Self {
+        let seed = Seed::new(mnemonic, passphrase.as_str());
+        AccountRoot {
+            node: derive_node(seed.as_bytes(), &SOLANA_PREFIX),
+        }
+    }
+
+    /// The keypair of `account`, at `m/44'/501'/account'/0'`.
+    pub fn account(&self, account: u32) -> Keypair {
+        node_keypair(&child_node(&child_node(&self.node, account), 0))
+    }
+}
+
+/// Wraps the phrase of a matching `mnemonic` for the results.
+pub fn seed_phrase(mnemonic: &Mnemonic, path: &DerivationPath) -> SeedPhrase {
+    SeedPhrase {
+        phrase: Zeroizing::new(mnemonic.phrase().to_owned()),
+        path: path.clone(),
+    }
+}
+
+/// SLIP-0010 derivation of the node at `path` from a BIP39 seed. The left
+/// half of a node is its ed25519 secret key and the right half its chain
+/// code.
+fn derive_node(seed: &[u8], path: &[u32]) -> Zeroizing<[u8; 64]> {
+    let master = hmac_sha512(b"ed25519 seed", &[seed]);
+    path.iter()
+        .fold(master, |node, &index| child_node(&node, index))
+}
+
+/// The hardened child `index` of `node`.
+fn child_node(node: &[u8; 64], index: u32) -> Zeroizing<[u8; 64]> {
+    let (key, chain_code) = node.split_at(32);
+    hmac_sha512(chain_code, &[&[0], key, &(index | HARDENED).to_be_bytes()])
+}
+
+fn node_keypair(node: &[u8; 64]) -> Keypair {
+    keypair_from_seed(&node[..32]).expect("32 bytes are always a valid ed25519 seed")
+}
+
+/// HMAC-SHA512 of `parts` concatenated.
+fn hmac_sha512(key: &[u8], parts: &[&[u8]]) -> Zeroizing<[u8; 64]> {
+    let mut mac = <Hmac<Sha512> as Mac>::new_from_slice(key).expect("HMAC takes any key");
+    for part in parts {
+        mac.update(part);
+    }
+    Zeroizing::new(mac.finalize().into_bytes().into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hex::hex;
+    use solana_sdk::{
+        derivation_path::DerivationPath as SdkPath,
+        signature::Signer,
+        signer::keypair::{
+            generate_seed_from_seed_phrase_and_passphrase, keypair_from_seed_and_derivation_path,
+        },
+    };
+
+    #[test]
+    fn matches_slip_0010_test_vector_1() {
+        let seed = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15];
+        let cases = [
+            (
+                "m",
+                "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+            ),
+            (
+                "m/0'",
+                "68e0fe46dfb67e368c75379acec591dad19df3cde26e63b93a8e704f1dade7a3",
+            ),
+            (
+                "m/0'/1'",
+                "b1d0bad404bf35da785a64ca1ac54b2617211d2777696fbffaf208f746ae84f2",
+            ),
+            (
+                "m/0'/1'/2'",
+                "92a5b23c0b8a99e37d07df3fb9966917f5d06e02ddbd909c7e184371463e9fc9",
+            ),
+            (
+                "m/0'/1'/2'/2'",
+                "30d1dc7e5fc04c31219ab25a27ae00b50f6fd66622f6e9c913253d6511d1e662",
+            ),
+            (
+                "m/0'/1'/2'/2'/1000000000'",
+                "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+            ),
+        ];
+        for (path, secret) in cases {
+            let path: DerivationPath = path.parse().unwrap();
+            assert_eq!(hex(&derive_node(&seed, &path.0)[..32]), secret, "{}", path);
+        }
+    }
+
+    #[test]
+    fn bip39_seed_matches_the_reference_vector() {
+        let mnemonic = parse(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )
+        .unwrap();
+        assert_eq!(
+            hex(Seed::new(&mnemonic, "TREZOR").as_bytes()),
+            "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04"
+        );
+    }
+
+    #[test]
+    fn test_mnemonic_gives_the_wallet_address() {
+        // The address Phantom and Solflare show for the BIP39 test phrase.
+        let mnemonic = parse(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+        )
+        .unwrap();
+        let keypair = derive_keypair(
+            &mnemonic,
+            &Password::new(String::new()),
+            &DerivationPath::solana(0),
+        );
+        assert_eq!(
+            keypair.pubkey().to_string(),
+            "HAgk14JpMQLgt6rVgv7cBQFJWFto5Dqxi472uT3DKpqk"
+        );
+    }
+
+    #[test]
+    fn keys_match_solana_keygen_derivation() {
+        for (words, passphrase, account) in [
+            (WordCount::Twelve, "", 0),
+            (WordCount::TwentyFour, "hunter2", 7),
+        ] {
+            let mnemonic = generate(words);
+            let path = DerivationPath::solana(account);
+            let keypair = derive_keypair(&mnemonic, &Password::new(passphrase.to_string()), &path);
+
+            let seed = generate_seed_from_seed_phrase_and_passphrase(mnemonic.phrase(), passphrase);
+            let expected = keypair_from_seed_and_derivation_path(
+                &seed,
+                Some(SdkPath::from_absolute_path_str(&path.to_string()).unwrap()),
+            )
+            .unwrap();
+            assert_eq!(keypair.pubkey(), expected.pubkey());
+
+            let root = AccountRoot::new(&mnemonic, &Password::new(passphrase.to_string()));
+            assert_eq!(root.account(account).pubkey(), expected.pubkey());
+        }
+    }
+
+    #[test]
+    fn paths_parse_and_print() {
+        let path: DerivationPath = "m/44'/501'/3'/0'".parse().unwrap();
+        assert_eq!(path, DerivationPath::solana(3));
+        assert_eq!(path.to_string(), "m/44'/501'/3'/0'");
+        assert!("m/44'/501".parse::<DerivationPath>().is_err());
+        assert!("44'/501'".parse::<DerivationPath>().is_err());
+    }
+}
@@ -0,0 +1,1077 @@
+use crate::{
+    keystore::{self, KeyKind, Password},
+    scrypt::Params,
+    seal::{self, Recipient},
+    search::{FoundKey, Generator, SearchConfig},
+    secret::SecretKey,
+    state::SearchState,
+};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::{Instant, UNIX_EPOCH},
+};
+use zeroize::Zeroizing;
+
+const CSV_HEADER: [&str; 4] = ["Public Key", "Private Key", "Mode", "Pattern"];
+/// Extra columns of a mnemonic search's CSV file.
+const CSV_SEED_PHRASE_HEADER: [&str; 2] = ["Seed Phrase", "Derivation Path"];
+/// The columns of a program address search's CSV file, which has no keys.
+const CSV_PDA_HEADER: [&str; 6] = ["Address", "Program ID", "Seeds", "Bump", "Mode", "Pattern"];
+/// The columns of a `create_with_seed` search's CSV file.
+const CSV_WITH_SEED_HEADER: [&str; 6] = ["Address", "Base", "Seed", "Owner", "Mode", "Pattern"];
+/// The columns of a split-key search's CSV file.
+const CSV_SPLIT_KEY_HEADER: [&str; 5] = ["Address", "Partial Key", "Offset", "Mode", "Pattern"];
+
+/// Kinds of output a run can write matches to; several can be combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Comma-separated `Public Key,Private Key,Mode,Pattern` rows, plus
+    /// `Seed Phrase,Derivation Path` for mnemonic searches, or
+    /// `Address,Program ID,Seeds,Bump,Mode,Pattern` for program addresses
+    /// or `Address,Base,Seed,Owner,Mode,Pattern` for `create_with_seed`
+    /// or `Address,Partial Key,Offset,Mode,Pattern` for split keys
+    Csv,
+    /// One JSON object per line with the key, pattern, mode, attempts,
+    /// timestamp and thread id, and the seed phrase and derivation path
+    /// for mnemonic searches, the program, seeds and bump for program
+    /// addresses, the base, seed and owner for `create_with_seed` or the
+    /// partial key and offset for split keys
+    Jsonl,
+    /// JSON lines on standard output, for piping into other tools
+    Stdout,
+    /// A `solana-keygen` keypair file per match, in `--keypair-dir`
+    Keypair,
+    /// A password-encrypted keystore file per match, in `--keystore-dir`
+    Keystore,
+    /// JSON lines encrypted to `--recipient` public keys, in
+    /// `--sealed-output`
+    Sealed,
+}
+
+/// One place matches are written to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    Csv(PathBuf),
+    JsonLines(PathBuf),
+    Stdout,
+    KeypairDir(PathBuf),
+    KeystoreDir(PathBuf),
+    Sealed {
+        path: PathBuf,
+        recipients: Vec<Recipient>,
+    },
+}
+
+impl fmt::Display for Output {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Output::Csv(path) | Output::JsonLines(path) | Output::Sealed { path, .. } => {
+                write!(f, "{}", path.display())
+            }
+            Output::Stdout => f.write_str("standard output"),
+            Output::KeypairDir(dir) => write!(f, "{}/<pubkey>.json", dir.display()),
+            Output::KeystoreDir(dir) => write!(f, "{}/<pubkey>.keystore.json", dir.display()),
+        }
+    }
+}
+
+/// What to do when the results file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteMode {
+    /// Refuse to touch an existing file
+    Create,
+    /// Add rows to an existing file with the same header
+    Append,
+    /// Replace an existing file
+    Overwrite,
+}
+
+/// Somewhere the writer thread sends matches.
+pub trait Sink: Send {
+    fn write(&mut self, found: &FoundKey) -> io::Result<()>;
+
+    /// Pushes out anything buffered. Called whenever the writer has caught
+    /// up with the workers. Sinks that write straight through, so no
+    /// secrets linger in their buffers, have nothing to do.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Flushes and makes the output durable once the search is over.
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+/// Opens a sink for every output up front, so an output that can't be
+/// written is reported before any keys are generated.
+///
+/// `generator` decides the columns of CSV files.
+///
+/// Panics if `outputs` includes a keystore but no `password` is given.
+pub fn open_sinks(
+    outputs: &[Output],
+    write_mode: WriteMode,
+    mode: &str,
+    generator: Generator,
+    password: Option<&Password>,
+) -> io::Result<Vec<Box<dyn Sink>>> {
+    outputs
+        .iter()
+        .map(|output| -> io::Result<Box<dyn Sink>> {
+            Ok(match output {
+                Output::Csv(path) => Box::new(CsvSink::open(path, write_mode, mode, generator)?),
+                Output::JsonLines(path) => Box::new(JsonLinesSink::open(path, write_mode, mode)?),
+                Output::Stdout => Box::new(StdoutSink::new(mode)),
+                Output::KeypairDir(dir) => Box::new(KeypairDirSink::open(dir)?),
+                Output::KeystoreDir(dir) => Box::new(KeystoreSink::open(
+                    dir,
+                    password.expect("keystores need a password").clone(),
+                    Params::RECOMMENDED,
+                )?),
+                Output::Sealed { path, recipients } => Box::new(SealedSink::open(
+                    path,
+                    write_mode,
+                    mode,
+                    recipients.clone(),
+                )?),
+            })
+        })
+        .collect()
+}
+
+/// Writes every match received on `rx` to all `sinks`, flushing them
+/// whenever the channel runs dry.
+///
+/// A sink that fails doesn't stop the others from getting the remaining
+/// matches; the first error is returned once the channel is closed.
+pub fn start_writer_thread(
+    rx: mpsc::Receiver<FoundKey>,
+    mut sinks: Vec<Box<dyn Sink>>,
+) -> thread::JoinHandle<io::Result<()>> {
+    thread::spawn(move || {
+        let mut result = Ok(());
+        let mut record = |outcome: io::Result<()>| {
+            if let Err(e) = outcome {
+                eprintln!("\nerror: cannot write result: {}", e);
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        };
+
+        while let Ok(found) = rx.recv() {
+            for found in std::iter::once(found).chain(rx.try_iter()) {
+                for sink in &mut sinks {
+                    record(sink.write(&found));
+                }
+            }
+            for sink in &mut sinks {
+                record(sink.flush());
+            }
+        }
+        // The summary says the keys were saved, so make sure they're on disk
+        for sink in &mut sinks {
+            record(sink.finish());
+        }
+        result
+    })
+}
+
+/// Ends a search whose workers have exited, because every match was found,
+/// the time ran out or `state` was interrupted: waits for `writer` to write,
+/// flush and sync every match already found, then reports how the search
+/// went to `out`. Nothing found before the stop is lost.
+///
+/// Returns the writer's error, if any, once the report is out.
+pub fn finish_search(
+    writer: thread::JoinHandle<io::Result<()>>,
+    state: &SearchState,
+    config: &SearchConfig,
+    start_time: Instant,
+    out: &mut dyn Write,
+) -> io::Result<()> {
+    let written = writer.join().expect("writer thread panicked");
+    report_completion(out, state, config, start_time)?;
+    written
+}
+
+fn report_completion(
+    out: &mut dyn Write,
+    state: &SearchState,
+    config: &SearchConfig,
+    start_time: Instant,
+) -> io::Result<()> {
+    let wallet_count_target = config.wallet_count_target();
+    let found_counts = state.found();
+    let found: u64 = found_counts.iter().sum();
+    if found >= wallet_count_target {
+        writeln!(out, "\nFound all {} vanity addresses!", wallet_count_target)?;
+    } else {
+        writeln!(
+            out,
+            "\nFound {} out of {} vanity addresses.",
+            found, wallet_count_target
+        )?;
+        if state.was_interrupted() {
+            writeln!(out, "Stopped: interrupted.")?;
+        } else if config.time_limit.is_some() {
+            writeln!(out, "Stopped: time limit reached.")?;
+        }
+    }
+    if config.targets.len() > 1 {
+        for (target, found) in config.targets.iter().zip(found_counts.iter()) {
+            writeln!(out, "  `{}`: {}/{}", target.pattern, found, target.count)?;
+        }
+    }
+    if config.generator == Generator::Mnemonic {
+        writeln!(out, "Seed phrases generated: {}", state.seeds())?;
+    }
+    writeln!(out, "Total wallets generated: {}", state.generated())?;
+    writeln!(out, "Elapsed time: {:?}", start_time.elapsed())?;
+    for output in &config.outputs {
+        if *output != Output::Stdout {
+            writeln!(out, "Results have been saved to {}", output)?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens `path` as `write_mode` says.
+///
+/// Results files hold secret keys, so a file this creates is readable only
+/// by its owner, like [`create_private_file`]'s.
+fn open_results_file(path: &Path, write_mode: WriteMode) -> io::Result<File> {
+    match write_mode {
+        WriteMode::Create => create_private_file(path).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => io::Error::new(
+                e.kind(),
+                format!(
+                    "`{}` already exists; use --append to add to it or --force to overwrite it",
+                    path.display()
+                ),
+            ),
+            _ => e,
+        }),
+        WriteMode::Append => private_options()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(path),
+        WriteMode::Overwrite => private_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path),
+    }
+}
+
+/// `Public Key,Private Key,Mode,Pattern` rows, with
+/// `Seed Phrase,Derivation Path` after them for mnemonic searches,
+/// `Address,Program ID,Seeds,Bump,Mode,Pattern` rows for program addresses,
+/// `Address,Base,Seed,Owner,Mode,Pattern` rows for addresses created
+/// with a seed, or `Address,Partial Key,Offset,Mode,Pattern` rows for
+/// split keys.
+pub struct CsvSink {
+    file: File,
+    mode: String,
+}
+
+impl CsvSink {
+    /// Opens `path`, checking the header of an existing file when
+    /// appending, and writes the header if the file is empty.
+    pub fn open(
+        path: &Path,
+        write_mode: WriteMode,
+        mode: &str,
+        generator: Generator,
+    ) -> io::Result<Self> {
+        let header = match generator {
+            Generator::Keypair | Generator::ScalarWalk => CSV_HEADER.join(","),
+            Generator::Mnemonic => [&CSV_HEADER[..], &CSV_SEED_PHRASE_HEADER]
+                .concat()
+                .join(","),
+            Generator::Pda => CSV_PDA_HEADER.join(","),
+            Generator::WithSeed => CSV_WITH_SEED_HEADER.join(","),
+            Generator::SplitKey => CSV_SPLIT_KEY_HEADER.join(","),
+        };
+        let mut file = open_results_file(path, write_mode)?;
+        if write_mode == WriteMode::Append {
+            check_csv_header(&file, path, &header)?;
+        }
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "{}", header)?;
+        }
+        Ok(CsvSink {
+            file,
+            mode: mode.to_owned(),
+        })
+    }
+}
+
+impl Sink for CsvSink {
+    fn write(&mut self, found: &FoundKey) -> io::Result<()> {
+        self.file.write_all(&csv_record(found, &self.mode))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
+/// Makes sure a non-empty file being appended to is one of ours, with the
+/// same columns.
+fn check_csv_header(file: &File, path: &Path, expected: &str) -> io::Result<()> {
+    let mut first_line = String::new();
+    BufReader::new(file).read_line(&mut first_line)?;
+    let header = first_line.trim_end_matches(['\r', '\n']);
+    if !first_line.is_empty() && header != expected {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "cannot append to `{}`: expected header `{}`, found `{}`",
+                path.display(),
+                expected,
+                header
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// The CSV row for a match, in a buffer that is wiped when dropped.
+fn csv_record(found: &FoundKey, mode: &str) -> Zeroizing<Vec<u8>> {
+    if let Some(pda) = &found.program_address {
+        let seeds: Vec<String> = pda.seeds.iter().map(ToString::to_string).collect();
+        return csv_line(&[
+            &found.public_key,
+            &pda.program_id.to_string(),
+            &seeds.join(" "),
+            &pda.bump.to_string(),
+            mode,
+            &found.pattern,
+        ]);
+    }
+    if let Some(account) = &found.account_seed {
+        return csv_line(&[
+            &found.public_key,
+            &account.base.to_string(),
+            &account.seed,
+            &account.owner.to_string(),
+            mode,
+            &found.pattern,
+        ]);
+    }
+    if let Some(split) = &found.split_key {
+        return csv_line(&[
+            &found.public_key,
+            &split.partial_key.to_string(),
+            &split.offset.to_string(),
+            mode,
+            &found.pattern,
+        ]);
+    }
+    let secret = found
+        .secret_key
+        .as_ref()
+        .expect("keys found by keypair searches have a secret key")
+        .to_base58();
+    let path = found
+        .seed_phrase
+        .as_ref()
+        .map(|phrase| phrase.path.to_string());
+    let mut fields = vec![
+        found.public_key.as_str(),
+        secret.as_str(),
+        mode,
+        &found.pattern,
+    ];
+    if let (Some(phrase), Some(path)) = (&found.seed_phrase, &path) {
+        fields.extend([phrase.phrase(), path.as_str()]);
+    }
+    csv_line(&fields)
+}
+
+/// `fields` quoted where needed and joined into a CSV line.
+fn csv_line(fields: &[&str]) -> Zeroizing<Vec<u8>> {
+    // Big enough for every field quoted, so the buffer never reallocates
+    // and leaves an unwiped copy of the secret behind.
+    let capacity = fields.iter().map(|field| 2 * field.len() + 3).sum();
+    let mut record = Zeroizing::new(Vec::with_capacity(capacity));
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            record.push(b',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            record.push(b'"');
+            record.extend_from_slice(field.replace('"', "\"\"").as_bytes());
+            record.push(b'"');
+        } else {
+            record.extend_from_slice(field.as_bytes());
+        }
+    }
+    record.push(b'\n');
+    record
+}
+
+/// The JSON object written for a match by the JSON Lines, stdout and
+/// sealed sinks.
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    pubkey: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<&'a str>,
+    pattern: &'a str,
+    mode: &'a str,
+    attempts: u64,
+    timestamp: u64,
+    thread_id: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mnemonic: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    derivation_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    program_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seeds: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bump: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    partial_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    offset: Option<String>,
+}
+
+/// The JSON line for a match, in a buffer that is wiped when dropped.
+fn json_record(found: &FoundKey, mode: &str) -> Zeroizing<Vec<u8>> {
+    let secret = found.secret_key.as_ref().map(SecretKey::to_base58);
+    let pda = found.program_address.as_ref();
+    let account = found.account_seed.as_ref();
+    let record = JsonRecord {
+        pubkey: &found.public_key,
+        secret: secret.as_ref().map(|secret| secret.as_str()),
+        pattern: &found.pattern,
+        mode,
+        attempts: found.attempts,
+        timestamp: found
+            .found_at
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs()),
+        thread_id: found.worker,
+        mnemonic: found.seed_phrase.as_ref().map(|phrase| phrase.phrase()),
+        derivation_path: found
+            .seed_phrase
+            .as_ref()
+            .map(|phrase| phrase.path.to_string()),
+        program_id: pda.map(|pda| pda.program_id.to_string()),
+        seeds: pda.map(|pda| pda.seeds.iter().map(ToString::to_string).collect()),
+        bump: pda.map(|pda| pda.bump),
+        base: account.map(|account| account.base.to_string()),
+        seed: account.map(|account| account.seed.as_str()),
+        owner: account.map(|account| account.owner.to_string()),
+        partial_key: found.split_key.map(|split| split.partial_key.to_string()),
+        offset: found.split_key.map(|split| split.offset.to_string()),
+    };
+    // Escaping grows a character to at most six bytes; reserving for that
+    // keeps the buffer from reallocating. Derived addresses have no secret
+    // to leave behind.
+    let seed_phrase_len = found.seed_phrase.as_ref().map_or(0, |phrase| {
+        64 + phrase.phrase().len() + phrase.path.to_string().len()
+    });
+    let capacity = 256
+        + 6 * (found.public_key.len()
+            + secret.as_ref().map_or(0, |secret| secret.len())
+            + found.pattern.len()
+            + mode.len()
+            + seed_phrase_len);
+    let mut line = Zeroizing::new(Vec::with_capacity(capacity));
+    serde_json::to_writer(&mut *line, &record).expect("records always serialize");
+    line.push(b'\n');
+    line
+}
+
+/// One JSON object per match, one per line.
+pub struct JsonLinesSink {
+    file: File,
+    mode: String,
+}
+
+impl JsonLinesSink {
+    pub fn open(path: &Path, write_mode: WriteMode, mode: &str) -> io::Result<Self> {
+        Ok(JsonLinesSink {
+            file: open_results_file(path, write_mode)?,
+            mode: mode.to_owned(),
+        })
+    }
+}
+
+impl Sink for JsonLinesSink {
+    fn write(&mut self, found: &FoundKey) -> io::Result<()> {
+        self.file.write_all(&json_record(found, &self.mode))
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
+/// JSON lines [sealed](crate::seal) to recipients' public keys, so the
+/// file can't be read on the machine that wrote it.
+pub struct SealedSink {
+    file: File,
+    mode: String,
+    recipients: Vec<Recipient>,
+}
+
+impl SealedSink {
+    /// Panics if `recipients` is empty.
+    pub fn open(
+        path: &Path,
+        write_mode: WriteMode,
+        mode: &str,
+        recipients: Vec<Recipient>,
+    ) -> io::Result<Self> {
+        assert!(!recipients.is_empty(), "sealing needs a recipient");
+        Ok(SealedSink {
+            file: open_results_file(path, write_mode)?,
+            mode: mode.to_owned(),
+            recipients,
+        })
+    }
+}
+
+impl Sink for SealedSink {
+    fn write(&mut self, found: &FoundKey) -> io::Result<()> {
+        writeln!(
+            self.file,
+            "{}",
+            sealed_record(found, &self.mode, &self.recipients)
+        )
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
+/// The JSON line for a match sealed to `recipients`, without the newline.
+///
+/// Panics if `recipients` is empty.
+pub fn sealed_record(found: &FoundKey, mode: &str, recipients: &[Recipient]) -> String {
+    let plaintext = json_record(found, mode);
+    seal::seal(
+        &found.public_key,
+        plaintext.strip_suffix(b"\n").unwrap_or(&plaintext),
+        recipients,
+    )
+}
+
+/// JSON lines on standard output.
+///
+/// Standard output keeps its own buffer, which isn't wiped.
+pub struct StdoutSink {
+    mode: String,
+}
+
+impl StdoutSink {
+    pub fn new(mode: &str) -> Self {
+        StdoutSink {
+            mode: mode.to_owned(),
+        }
+    }
+}
+
+impl Sink for StdoutSink {
+    fn write(&mut self, found: &FoundKey) -> io::Result<()> {
+        io::stdout()
+            .lock()
+            .write_all(&json_record(found, &self.mode))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::stdout().flush()
+    }
+}
+
+/// A `solana-keygen` keypair file per match.
+pub struct KeypairDirSink {
+    dir: PathBuf,
+}
+
+impl KeypairDirSink {
+    /// Creates `dir` if it doesn't exist yet.
+    pub fn open(dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(KeypairDirSink {
+            dir: dir.to_owned(),
+        })
+    }
+}
+
+impl Sink for KeypairDirSink {
+    fn write(&mut self, found: &FoundKey) -> io::Result<()> {
+        write_keypair_file(&self.dir, found).map(drop)
+    }
+}
+
+/// A password-encrypted [keystore](crate::keystore) file per match.
+pub struct KeystoreSink {
+    dir: PathBuf,
+    password: Password,
+    params: Params,
+}
+
+impl KeystoreSink {
+    /// Creates `dir` if it doesn't exist yet.
+    pub fn open(dir: &Path, password: Password, params: Params) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        Ok(KeystoreSink {
+            dir: dir.to_owned(),
+            password,
+            params,
+        })
+    }
+}
+
+impl Sink for KeystoreSink {
+    fn write(&mut self, found: &FoundKey) -> io::Result<()> {
+        let kind = if found.expanded {
+            KeyKind::Expanded
+        } else {
+            KeyKind::Keypair
+        };
+        let contents = keystore::encrypt(
+            &found.public_key,
+            kind,
+            secret_key(found)?.as_bytes(),
+            &self.password,
+            self.params,
+        );
+
+        let path = self.dir.join(format!("{}.keystore.json", found.public_key));
+        let mut file = create_private_file(&path)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    }
+}
+
+/// The secret key of `found`, for sinks that write nothing else.
+fn secret_key(found: &FoundKey) -> io::Result<&SecretKey> {
+    found.secret_key.as_ref().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "derived addresses have no secret key",
+        )
+    })
+}
+
+/// Creates a new file only its owner can read, failing if it exists.
+pub fn create_private_file(path: &Path) -> io::Result<File> {
+    private_options().write(true).create_new(true).open(path)
+}
+
+/// Options that create files only their owner can read.
+fn private_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+}
+
+/// Writes `found` to `<pubkey>.json` in `dir` as the JSON array of secret
+/// bytes `solana-keygen` uses, readable only by its owner. Existing files
+/// are never overwritten.
+///
+/// Only works for keys from [`FoundKey::new`]; expanded keys have no seed
+/// to write.
+pub fn write_keypair_file(dir: &Path, found: &FoundKey) -> io::Result<PathBuf> {
+    if found.expanded {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "expanded keys can't be written as keypair files",
+        ));
+    }
+    let secret_key = secret_key(found)?;
+    let path = dir.join(format!("{}.json", found.public_key));
+    let mut file = create_private_file(&path)?;
+    file.write_all(secret_key.to_keypair_json().as_bytes())?;
+    file.sync_all()?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mnemonic::{self, DerivationPath, WordCount},
+        pda::{ProgramAddress, Seed},
+        search::Target,
+        split_key::{Offset, SplitMatch, SplitSecret},
+        with_seed::AccountSeed,
+    };
+    use solana_sdk::{
+        pubkey::Pubkey,
+        signature::{Keypair, Signer},
+    };
+
+    fn found(keypair: &Keypair) -> FoundKey {
+        FoundKey::new(keypair, keypair.pubkey().to_string(), "A", 2, 5000)
+    }
+
+    fn write_keys(sink: io::Result<impl Sink + 'static>, keys: &[FoundKey]) -> io::Result<()> {
+        let (tx, rx) = mpsc::channel();
+        for key in keys {
+            tx.send(key.clone()).unwrap();
+        }
+        drop(tx);
+        start_writer_thread(rx, vec![Box::new(sink?)])
+            .join()
+            .unwrap()
+    }
+
+    fn secret(key: &FoundKey) -> String {
+        key.secret_key.as_ref().unwrap().to_base58().to_string()
+    }
+
+    fn csv_row(key: &FoundKey) -> String {
+        format!("{},{},prefix,A\n", key.public_key, secret(key))
+    }
+
+    #[test]
+    fn header_is_written_once_and_existing_files_are_kept() {
+        let path = std::env::temp_dir().join(format!("vanaddy-output-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let keys: Vec<FoundKey> = (0..4).map(|_| found(&Keypair::new())).collect();
+        let open = |write_mode| CsvSink::open(&path, write_mode, "prefix", Generator::Keypair);
+
+        write_keys(open(WriteMode::Create), &keys[..1]).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let err = write_keys(open(WriteMode::Create), &keys[1..2]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        write_keys(open(WriteMode::Append), &keys[2..3]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!(
+                "Public Key,Private Key,Mode,Pattern\n{}{}",
+                csv_row(&keys[0]),
+                csv_row(&keys[2])
+            )
+        );
+
+        write_keys(open(WriteMode::Overwrite), &keys[3..]).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            format!("Public Key,Private Key,Mode,Pattern\n{}", csv_row(&keys[3]))
+        );
+
+        fs::write(&path, "address,key\n").unwrap();
+        let err = write_keys(open(WriteMode::Append), &keys[..1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn csv_fields_with_separators_are_quoted() {
+        let keypair = Keypair::new();
+        let key = FoundKey::new(&keypair, keypair.pubkey().to_string(), "^A{1,2}\"", 0, 0);
+        assert_eq!(
+            *csv_record(&key, "regex"),
+            format!(
+                "{},{},regex,\"^A{{1,2}}\"\"\"\n",
+                key.public_key,
+                secret(&key)
+            )
+            .into_bytes()
+        );
+    }
+
+    #[test]
+    fn seed_phrases_are_written_with_their_path() {
+        let phrase = mnemonic::generate(WordCount::Twelve);
+        let path = DerivationPath::solana(0);
+        let keypair = mnemonic::derive_keypair(&phrase, &Password::new(String::new()), &path);
+        let key = found(&keypair).with_seed_phrase(mnemonic::seed_phrase(&phrase, &path));
+
+        let record: serde_json::Value =
+            serde_json::from_slice(&json_record(&key, "prefix")).unwrap();
+        assert_eq!(record["mnemonic"], phrase.phrase());
+        assert_eq!(record["derivation_path"], "m/44'/501'/0'/0'");
+        assert!(serde_json::from_slice::<serde_json::Value>(&json_record(
+            &found(&keypair),
+            "prefix"
+        ))
+        .unwrap()
+        .get("mnemonic")
+        .is_none());
+        assert_eq!(
+            *csv_record(&key, "prefix"),
+            format!(
+                "{},prefix,A,{},m/44'/501'/0'/0'\n",
+                csv_row(&key).trim_end_matches(",prefix,A\n"),
+                phrase.phrase()
+            )
+            .into_bytes()
+        );
+    }
+
+    #[test]
+    fn derived_addresses_are_written_without_secrets() {
+        let (program_id, base, owner) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let partial_key = SplitSecret::generate().partial_key();
+        let offset = Offset::new(7u64.into());
+        let program_address = ProgramAddress {
+            program_id,
+            seeds: vec![Seed::Str("vault".to_string()), Seed::U64(7)],
+            bump: 253,
+        };
+        let account_seed = AccountSeed {
+            base,
+            seed: "vault,7".to_string(),
+            owner,
+        };
+        let split_match = SplitMatch {
+            partial_key,
+            offset,
+        };
+        let cases = [
+            (
+                FoundKey::program_address(program_address, "Addr".to_string(), "A", 0, 0),
+                "prefix (program address)",
+                format!(
+                    "Addr,{},str:vault u64:7,253,prefix (program address),A\n",
+                    program_id
+                ),
+                serde_json::json!({ "seeds": ["str:vault", "u64:7"], "bump": 253 }),
+            ),
+            (
+                FoundKey::account_seed(account_seed, "Addr".to_string(), "A", 0, 0),
+                "prefix (with seed)",
+                format!("Addr,{},\"vault,7\",{},prefix (with seed),A\n", base, owner),
+                serde_json::json!({
+                    "base": base.to_string(),
+                    "seed": "vault,7",
+                    "owner": owner.to_string(),
+                }),
+            ),
+            (
+                FoundKey::split_key(split_match, "Addr".to_string(), "A", 0, 0),
+                "prefix (split key)",
+                format!("Addr,{},{},prefix (split key),A\n", partial_key, offset),
+                serde_json::json!({
+                    "partial_key": partial_key.to_string(),
+                    "offset": offset.to_string(),
+                }),
+            ),
+        ];
+
+        for (key, mode, csv, fields) in cases {
+            assert_eq!(*csv_record(&key, mode), csv.into_bytes(), "{}", mode);
+            let record: serde_json::Value =
+                serde_json::from_slice(&json_record(&key, mode)).unwrap();
+            for (name, value) in fields.as_object().unwrap() {
+                assert_eq!(&record[name], value, "{} {}", mode, name);
+            }
+            assert_eq!(record["pubkey"], "Addr");
+            assert!(record.get("secret").is_none(), "{}", mode);
+            assert!(secret_key(&key).is_err());
+        }
+    }
+
+    #[test]
+    fn json_lines_hold_every_field() {
+        let path =
+            std::env::temp_dir().join(format!("vanaddy-output-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let keys = [found(&Keypair::new()), found(&Keypair::new())];
+
+        write_keys(
+            JsonLinesSink::open(&path, WriteMode::Overwrite, "prefix"),
+            &keys,
+        )
+        .unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        let contents = fs::read_to_string(&path).unwrap();
+        let lines: Vec<serde_json::Value> = contents
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["pubkey"], keys[0].public_key);
+        assert_eq!(lines[0]["secret"], secret(&keys[0]));
+        assert_eq!(lines[0]["pattern"], "A");
+        assert_eq!(lines[0]["mode"], "prefix");
+        assert_eq!(lines[0]["attempts"], 5000);
+        assert_eq!(lines[0]["thread_id"], 2);
+        assert!(lines[0]["timestamp"].as_u64().unwrap() > 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn sealed_lines_open_with_an_identity() {
+        let path =
+            std::env::temp_dir().join(format!("vanaddy-output-{}.sealed", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let identity = seal::Identity::generate();
+        let keys = [found(&Keypair::new()), found(&Keypair::new())];
+
+        let sink = SealedSink::open(
+            &path,
+            WriteMode::Create,
+            "prefix",
+            vec![identity.recipient()],
+        );
+        write_keys(sink, &keys).unwrap();
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(secret(&keys[0]).as_str()));
+        for (line, key) in contents.lines().zip(&keys) {
+            let (public_key, plaintext) = seal::open(line, &identity).unwrap();
+            assert_eq!(public_key, key.public_key);
+            let record: serde_json::Value = serde_json::from_slice(&plaintext).unwrap();
+            assert_eq!(record["secret"], secret(key));
+        }
+        assert!(seal::open(
+            contents.lines().next().unwrap(),
+            &seal::Identity::generate()
+        )
+        .is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn interrupted_searches_save_their_matches_and_say_so() {
+        let path =
+            std::env::temp_dir().join(format!("vanaddy-interrupt-{}.csv", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut config = SearchConfig::new(
+            vec![
+                Target {
+                    pattern: "A".to_string(),
+                    count: 2,
+                },
+                Target {
+                    pattern: "B".to_string(),
+                    count: 1,
+                },
+            ],
+            Generator::Keypair,
+        );
+        config.outputs = vec![Output::Csv(path.clone()), Output::Stdout];
+        let state = SearchState::new(config.counts(), 1);
+        let sinks = open_sinks(
+            &[Output::Csv(path.clone())],
+            WriteMode::Create,
+            "prefix",
+            Generator::Keypair,
+            None,
+        )
+        .unwrap();
+        let (tx, rx) = mpsc::channel();
+        let writer = start_writer_thread(rx, sinks);
+
+        let key = found(&Keypair::new());
+        assert_eq!(state.claim(&[0]), Some(0));
+        state.add_generated(0, 1000);
+        tx.send(key.clone()).unwrap();
+        state.interrupt();
+        drop(tx);
+
+        let mut out = Vec::new();
+        finish_search(writer, &state, &config, Instant::now(), &mut out).unwrap();
+        let report = String::from_utf8(out).unwrap();
+        assert!(
+            report.contains("Found 1 out of 3 vanity addresses."),
+            "{}",
+            report
+        );
+        assert!(report.contains("Stopped: interrupted."), "{}", report);
+        assert!(
+            report.contains("`A`: 1/2") && report.contains("`B`: 0/1"),
+            "{}",
+            report
+        );
+        assert!(
+            report.contains("Total wallets generated: 1000"),
+            "{}",
+            report
+        );
+        assert!(report.contains(&format!("Results have been saved to {}", path.display())));
+        assert!(fs::read_to_string(&path).unwrap().ends_with(&csv_row(&key)));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn keystore_files_decrypt_with_the_password() {
+        let dir = std::env::temp_dir().join(format!("vanaddy-keystores-{}", std::process::id()));
+        let keypair = Keypair::new();
+        let password = Password::new("hunter2".to_string());
+        let fast = Params {
+            log_n: 4,
+            r: 8,
+            p: 1,
+        };
+
+        write_keys(
+            KeystoreSink::open(&dir, password.clone(), fast),
+            &[found(&keypair)],
+        )
+        .unwrap();
+        let path = dir.join(format!("{}.keystore.json", keypair.pubkey()));
+        let key = keystore::decrypt(&fs::read_to_string(&path).unwrap(), &password).unwrap();
+        assert_eq!(key.secret.as_bytes(), &keypair.to_bytes());
+        assert_eq!(key.kind, KeyKind::Keypair);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn keypair_files_load_with_the_sdk() {
+        let dir = std::env::temp_dir().join(format!("vanaddy-keypairs-{}", std::process::id()));
+        let keypair = Keypair::new();
+        let key = found(&keypair);
+
+        write_keys(KeypairDirSink::open(&dir), std::slice::from_ref(&key)).unwrap();
+        let path = dir.join(format!("{}.json", keypair.pubkey()));
+        let restored = solana_sdk::signer::keypair::read_keypair_file(&path).unwrap();
+        assert_eq!(restored.to_bytes(), keypair.to_bytes());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert!(write_keypair_file(&dir, &key).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
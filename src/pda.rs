@@ -0,0 +1,283 @@
+//! Vanity program derived addresses.
+//!
+//! A PDA is the hash of a program ID and a list of seeds, bumped off the
+//! curve so that no private key exists for it. The search keeps the program
+//! ID and every seed but one fixed and varies that one, the search seed,
+//! taking the canonical bump `find_program_address` would pick for each
+//! candidate. A match is just the seeds and bump to derive it, so nothing
+//! secret is written.
+//!
+//! Seeds are written as `KIND:VALUE` both on the command line and in the
+//! results: `str:vault`, `pubkey:<base58>`, `hex:00ff`, or a little-endian
+//! integer `u8:1`, `u16:1`, `u32:1`, `u64:1`. The search seed is one of
+//! `counter` (a `u64`), `random:N` (`N` random bytes) or `suffix:TEXT`
+//! (`TEXT` followed by a decimal counter).
+
+use crate::hex::{hex, parse_hex};
+use rand::{rngs::ThreadRng, RngCore};
+use solana_sdk::pubkey::{Pubkey, MAX_SEEDS, MAX_SEED_LEN};
+use std::{borrow::Cow, fmt, str::FromStr};
+
+/// Room left for the counter after a `suffix:` seed's text; a `u64` has at
+/// most 20 decimal digits.
+const MAX_SUFFIX_PREFIX_LEN: usize = MAX_SEED_LEN - 20;
+
+/// One seed of a program address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Seed {
+    Str(String),
+    Pubkey(Pubkey),
+    Bytes(Vec<u8>),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+}
+
+impl Seed {
+    pub fn bytes(&self) -> Cow<'_, [u8]> {
+        match self {
+            Seed::Str(text) => Cow::Borrowed(text.as_bytes()),
+            Seed::Pubkey(pubkey) => Cow::Borrowed(pubkey.as_ref()),
+            Seed::Bytes(bytes) => Cow::Borrowed(bytes),
+            Seed::U8(n) => Cow::Owned(n.to_le_bytes().to_vec()),
+            Seed::U16(n) => Cow::Owned(n.to_le_bytes().to_vec()),
+            Seed::U32(n) => Cow::Owned(n.to_le_bytes().to_vec()),
+            Seed::U64(n) => Cow::Owned(n.to_le_bytes().to_vec()),
+        }
+    }
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // Seeds are listed separated by spaces, so text that would
+            // blur the list is written as bytes.
+            Seed::Str(text) if !text.chars().any(|c| c.is_whitespace() || c.is_control()) => {
+                write!(f, "str:{}", text)
+            }
+            Seed::Str(text) => write!(f, "hex:{}", hex(text.as_bytes())),
+            Seed::Pubkey(pubkey) => write!(f, "pubkey:{}", pubkey),
+            Seed::Bytes(bytes) => write!(f, "hex:{}", hex(bytes)),
+            Seed::U8(n) => write!(f, "u8:{}", n),
+            Seed::U16(n) => write!(f, "u16:{}", n),
+            Seed::U32(n) => write!(f, "u32:{}", n),
+            Seed::U64(n) => write!(f, "u64:{}", n),
+        }
+    }
+}
+
+impl FromStr for Seed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (kind, value) = s
+            .split_once(':')
+            .ok_or_else(|| format!("seed `{}` must be `KIND:VALUE`", s))?;
+        let invalid = |e: &dyn fmt::Display| format!("invalid {} seed `{}`: {}", kind, value, e);
+        let seed = match kind {
+            "str" => Seed::Str(value.to_owned()),
+            "pubkey" => Seed::Pubkey(value.parse().map_err(|e| invalid(&e))?),
+            "hex" => Seed::Bytes(parse_hex(value).map_err(|e| invalid(&e))?),
+            "u8" => Seed::U8(value.parse().map_err(|e| invalid(&e))?),
+            "u16" => Seed::U16(value.parse().map_err(|e| invalid(&e))?),
+            "u32" => Seed::U32(value.parse().map_err(|e| invalid(&e))?),
+            "u64" => Seed::U64(value.parse().map_err(|e| invalid(&e))?),
+            _ => return Err(format!("unknown seed kind `{}`", kind)),
+        };
+        if seed.bytes().len() > MAX_SEED_LEN {
+            return Err(format!(
+                "seed `{}` is longer than {} bytes",
+                s, MAX_SEED_LEN
+            ));
+        }
+        Ok(seed)
+    }
+}
+
+/// A seed given on the command line: fixed, or the one the search varies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedSpec {
+    Fixed(Seed),
+    /// A `u64` counting up from zero
+    Counter,
+    /// This many random bytes
+    Random(usize),
+    /// The text followed by a decimal counter
+    Suffix(String),
+}
+
+impl SeedSpec {
+    fn is_search(&self) -> bool {
+        !matches!(self, SeedSpec::Fixed(_))
+    }
+}
+
+impl FromStr for SeedSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        if s == "counter" {
+            return Ok(SeedSpec::Counter);
+        }
+        if let Some(len) = s.strip_prefix("random:") {
+            return match len.parse() {
+                Ok(len @ 1..=MAX_SEED_LEN) => Ok(SeedSpec::Random(len)),
+                _ => Err(format!("`random:N` needs N between 1 and {}", MAX_SEED_LEN)),
+            };
+        }
+        if let Some(text) = s.strip_prefix("suffix:") {
+            if text.len() > MAX_SUFFIX_PREFIX_LEN {
+                return Err(format!(
+                    "`suffix:` text can be at most {} bytes to leave room for the counter",
+                    MAX_SUFFIX_PREFIX_LEN
+                ));
+            }
+            return Ok(SeedSpec::Suffix(text.to_owned()));
+        }
+        s.parse().map(SeedSpec::Fixed)
+    }
+}
+
+/// The program and seeds of a program address search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PdaSettings {
+    pub program_id: Pubkey,
+    pub seeds: Vec<SeedSpec>,
+}
+
+impl PdaSettings {
+    /// Checks that exactly one seed is searched and that the seeds leave
+    /// room for the bump.
+    pub fn validate(&self) -> Result<(), String> {
+        match self.seeds.iter().filter(|seed| seed.is_search()).count() {
+            0 => return Err(
+                "a program address search needs one of `counter`, `random:N` or `suffix:TEXT` among the seeds"
+                    .to_string(),
+            ),
+            1 => {}
+            _ => return Err("only one seed can be searched".to_string()),
+        }
+        if self.seeds.len() >= MAX_SEEDS {
+            return Err(format!(
+                "at most {} seeds fit next to the bump, got {}",
+                MAX_SEEDS - 1,
+                self.seeds.len()
+            ));
+        }
+        Ok(())
+    }
+
+    /// The search seed for candidate number `counter`.
+    pub fn candidate(&self, counter: u64, rng: &mut ThreadRng) -> Seed {
+        match self.seeds.iter().find(|seed| seed.is_search()) {
+            Some(SeedSpec::Counter) => Seed::U64(counter),
+            Some(SeedSpec::Random(len)) => {
+                let mut bytes = vec![0u8; *len];
+                rng.fill_bytes(&mut bytes);
+                Seed::Bytes(bytes)
+            }
+            Some(SeedSpec::Suffix(text)) => Seed::Str(format!("{}{}", text, counter)),
+            _ => panic!("program address settings were not validated"),
+        }
+    }
+
+    /// Every seed with `candidate` in place of the search seed.
+    pub fn seeds_with(&self, candidate: &Seed) -> Vec<Seed> {
+        self.seeds
+            .iter()
+            .map(|seed| match seed {
+                SeedSpec::Fixed(seed) => seed.clone(),
+                _ => candidate.clone(),
+            })
+            .collect()
+    }
+
+    /// The canonical address and bump for `candidate`, if any bump gets the
+    /// address off the curve.
+    pub fn find(&self, candidate: &Seed) -> Option<(Pubkey, u8)> {
+        let bytes: Vec<Cow<[u8]>> = self
+            .seeds
+            .iter()
+            .map(|seed| match seed {
+                SeedSpec::Fixed(seed) => seed.bytes(),
+                _ => candidate.bytes(),
+            })
+            .collect();
+        let seeds: Vec<&[u8]> = bytes.iter().map(|seed| &seed[..]).collect();
+        Pubkey::try_find_program_address(&seeds, &self.program_id)
+    }
+}
+
+/// How to derive a matching program address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProgramAddress {
+    pub program_id: Pubkey,
+    pub seeds: Vec<Seed>,
+    pub bump: u8,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_find_program_address() {
+        let program_id = Pubkey::new_unique();
+        let authority = Pubkey::new_unique();
+        let settings = PdaSettings {
+            program_id,
+            seeds: vec![
+                "str:vault".parse().unwrap(),
+                format!("pubkey:{}", authority).parse().unwrap(),
+                "counter".parse().unwrap(),
+            ],
+        };
+        settings.validate().unwrap();
+
+        let candidate = settings.candidate(42, &mut rand::thread_rng());
+        let (address, bump) = settings.find(&candidate).unwrap();
+        assert_eq!(
+            (address, bump),
+            Pubkey::find_program_address(
+                &[b"vault", authority.as_ref(), &42u64.to_le_bytes()],
+                &program_id
+            )
+        );
+        assert!(!address.is_on_curve());
+
+        let seeds = settings.seeds_with(&candidate);
+        let listed: Vec<String> = seeds.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            listed,
+            [
+                "str:vault".to_string(),
+                format!("pubkey:{}", authority),
+                "u64:42".to_string()
+            ]
+        );
+        let parsed: Vec<Seed> = listed.iter().map(|seed| seed.parse().unwrap()).collect();
+        assert_eq!(parsed, seeds);
+    }
+
+    #[test]
+    fn seed_specs_are_checked() {
+        assert_eq!("hex:00ff".parse::<Seed>(), Ok(Seed::Bytes(vec![0, 0xff])));
+        assert_eq!(Seed::Str("a b".to_string()).to_string(), "hex:612062");
+        assert_eq!(
+            "suffix:vault-".parse(),
+            Ok(SeedSpec::Suffix("vault-".to_string()))
+        );
+        assert!("random:33".parse::<SeedSpec>().is_err());
+        assert!("hex:0".parse::<Seed>().is_err());
+        assert!(format!("str:{}", "a".repeat(33)).parse::<Seed>().is_err());
+
+        let settings = |seeds: &[&str]| PdaSettings {
+            program_id: Pubkey::new_unique(),
+            seeds: seeds.iter().map(|seed| seed.parse().unwrap()).collect(),
+        };
+        assert!(settings(&["str:vault"]).validate().is_err());
+        assert!(settings(&["counter", "random:4"]).validate().is_err());
+        assert!(settings(&["random:4"]).validate().is_ok());
+    }
+}
@@ -0,0 +1,271 @@
+use crate::{
+    base58::{self, PUBKEY_BYTES},
+    matcher::MatchPosition,
+};
+use num_bigint::BigUint;
+
+/// Longest suffix checked numerically; `58^9` still leaves room in a `u64`
+/// to shift in another byte.
+const MAX_SUFFIX_LEN: usize = 9;
+
+/// Number of leading bytes that must be zero before a key's value can drop
+/// below `58^MAX_SUFFIX_LEN`.
+const SMALL_KEY_ZERO_BYTES: usize = PUBKEY_BYTES - 8;
+
+/// Rules out public keys from their raw bytes, before paying for base58
+/// encoding.
+///
+/// A key whose address starts with a prefix lies in one of a few numeric
+/// ranges (see [`base58::prefix_ranges`]), and the last `k` characters of
+/// an address are the key's value modulo `58^k`. The filter only ever
+/// rejects keys that cannot match, so every key it lets through still has
+/// to be encoded and checked.
+#[derive(Debug, Clone, Default)]
+pub struct KeyFilter {
+    /// Sorted, disjoint, inclusive big-endian ranges, or `None` to accept
+    /// any key.
+    prefix: Option<Vec<([u8; PUBKEY_BYTES], [u8; PUBKEY_BYTES])>>,
+    /// Accepted remainders for each suffix length, or `None` to accept any
+    /// key.
+    suffix: Option<Vec<Suffixes>>,
+}
+
+#[derive(Debug, Clone)]
+struct Suffixes {
+    modulus: u64,
+    /// Sorted remainders modulo `modulus`.
+    values: Vec<u64>,
+}
+
+impl KeyFilter {
+    /// Builds the filter for literal patterns at `position`. Substring
+    /// matches can't be narrowed down this way, so `anywhere` accepts
+    /// every key.
+    pub fn new<S: AsRef<str>>(
+        patterns: &[S],
+        position: MatchPosition,
+        case_sensitive: bool,
+    ) -> Self {
+        let anchored_at_start = matches!(position, MatchPosition::Prefix | MatchPosition::Both);
+        let anchored_at_end = matches!(position, MatchPosition::Suffix | MatchPosition::Both)
+            && patterns
+                .iter()
+                .all(|pattern| pattern.as_ref().len() <= MAX_SUFFIX_LEN);
+        KeyFilter {
+            prefix: anchored_at_start.then(|| prefix_ranges(patterns, case_sensitive)),
+            suffix: anchored_at_end.then(|| suffixes(patterns, case_sensitive)),
+        }
+    }
+
+    /// A filter that lets every key through.
+    pub fn accept_all() -> Self {
+        KeyFilter::default()
+    }
+
+    /// Whether the address of `key` could match one of the patterns.
+    pub fn may_match(&self, key: &[u8; PUBKEY_BYTES]) -> bool {
+        if let Some(ranges) = &self.prefix {
+            let i = ranges.partition_point(|(_, end)| end < key);
+            if ranges.get(i).is_none_or(|(start, _)| start > key) {
+                return false;
+            }
+        }
+        if let Some(suffixes) = &self.suffix {
+            // Keys this small have fewer digits than the suffix, so their
+            // last characters include padding `1`s. Leave those to the matcher.
+            if key[..SMALL_KEY_ZERO_BYTES].iter().all(|&b| b == 0) {
+                return true;
+            }
+            return suffixes.iter().any(|suffix| {
+                let remainder = key
+                    .iter()
+                    .fold(0u64, |r, &b| ((r << 8) | b as u64) % suffix.modulus);
+                suffix.values.binary_search(&remainder).is_ok()
+            });
+        }
+        true
+    }
+}
+
+fn prefix_ranges<S: AsRef<str>>(
+    patterns: &[S],
+    case_sensitive: bool,
+) -> Vec<([u8; PUBKEY_BYTES], [u8; PUBKEY_BYTES])> {
+    let mut ranges: Vec<_> = patterns
+        .iter()
+        .flat_map(|pattern| base58::prefix_variants(pattern.as_ref(), case_sensitive))
+        .flat_map(|prefix| base58::prefix_ranges(&prefix))
+        .map(|(start, end)| (to_key_bytes(&start), to_key_bytes(&(end - 1u32))))
+        .collect();
+    ranges.sort_unstable();
+
+    let mut merged: Vec<([u8; PUBKEY_BYTES], [u8; PUBKEY_BYTES])> = Vec::new();
+    for (start, end) in ranges {
+        match merged.last_mut() {
+            Some((_, last_end)) if start <= *last_end => *last_end = end.max(*last_end),
+            _ => merged.push((start, end)),
+        }
+    }
+    merged
+}
+
+fn suffixes<S: AsRef<str>>(patterns: &[S], case_sensitive: bool) -> Vec<Suffixes> {
+    let mut suffixes: Vec<Suffixes> = Vec::new();
+    for variant in patterns
+        .iter()
+        .flat_map(|pattern| base58::prefix_variants(pattern.as_ref(), case_sensitive))
+    {
+        let value = variant.bytes().fold(0u64, |value, c| {
+            value * 58 + base58::digit(c).expect("variants are base58") as u64
+        });
+        let modulus = 58u64.pow(variant.len() as u32);
+        match suffixes.iter_mut().find(|s| s.modulus == modulus) {
+            Some(suffix) => suffix.values.push(value),
+            None => suffixes.push(Suffixes {
+                modulus,
+                values: vec![value],
+            }),
+        }
+    }
+    for suffix in &mut suffixes {
+        suffix.values.sort_unstable();
+        suffix.values.dedup();
+    }
+    suffixes
+}
+
+fn to_key_bytes(value: &BigUint) -> [u8; PUBKEY_BYTES] {
+    let bytes = value.to_bytes_be();
+    let mut key = [0u8; PUBKEY_BYTES];
+    key[PUBKEY_BYTES - bytes.len()..].copy_from_slice(&bytes);
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::matcher::PatternSet;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+    use solana_sdk::bs58;
+
+    const POSITIONS: [MatchPosition; 3] = [
+        MatchPosition::Prefix,
+        MatchPosition::Suffix,
+        MatchPosition::Both,
+    ];
+
+    /// Checks that `filter` lets through every key `set` matches, and
+    /// returns how many keys it rejected.
+    fn check(filter: &KeyFilter, set: &PatternSet, keys: &[[u8; PUBKEY_BYTES]]) -> usize {
+        let mut rejected = 0;
+        for key in keys {
+            let address = bs58::encode(key).into_string();
+            if !filter.may_match(key) {
+                assert!(
+                    set.matches(&address).is_empty(),
+                    "{:?} rejected {} for {:?}",
+                    set.patterns(),
+                    address,
+                    set.position()
+                );
+                rejected += 1;
+            }
+        }
+        rejected
+    }
+
+    /// Random keys, many with leading zero bytes so their addresses start
+    /// with `1`s or are short enough for the suffix padding case.
+    fn random_keys(rng: &mut StdRng, count: usize) -> Vec<[u8; PUBKEY_BYTES]> {
+        (0..count)
+            .map(|_| {
+                let mut key: [u8; PUBKEY_BYTES] = rng.gen();
+                let zeros = *[0, 0, 0, 0, 1, 2, 3, 24, 25, 30, 32].choose(rng).unwrap();
+                key[..zeros].fill(0);
+                key
+            })
+            .collect()
+    }
+
+    #[test]
+    fn never_rejects_a_matching_key() {
+        let mut rng = StdRng::seed_from_u64(8);
+        // Short patterns so plenty of keys match; leading `1`s, and letters
+        // that only have one case in base58.
+        let chars = b"12ABJKabzLlOoi";
+        let mut rejected = 0;
+        for round in 0..60 {
+            let patterns: Vec<String> = (0..rng.gen_range(1..4))
+                .map(|_| {
+                    let len = rng.gen_range(1..3);
+                    (0..len)
+                        .map(|_| *chars.choose(&mut rng).unwrap() as char)
+                        .collect()
+                })
+                .collect();
+            let case_sensitive = round % 2 == 0;
+            let keys = random_keys(&mut rng, 500);
+            for position in POSITIONS {
+                let set = PatternSet::new(&patterns, position, case_sensitive);
+                let filter = KeyFilter::new(&patterns, position, case_sensitive);
+                rejected += check(&filter, &set, &keys);
+            }
+        }
+        assert!(rejected > 0, "the filter never rejected anything");
+    }
+
+    #[test]
+    fn range_edges_are_exact() {
+        for (pattern, case_sensitive) in [
+            ("1", true),
+            ("11z", true),
+            ("2", true),
+            ("J", true),
+            ("SoL", false),
+            ("abc", false),
+        ] {
+            let set = PatternSet::new(&[pattern], MatchPosition::Prefix, case_sensitive);
+            let filter = KeyFilter::new(&[pattern], MatchPosition::Prefix, case_sensitive);
+            let mut keys = Vec::new();
+            for variant in base58::prefix_variants(pattern, case_sensitive) {
+                for (start, end) in base58::prefix_ranges(&variant) {
+                    let last = &end - 1u32;
+                    keys.extend([to_key_bytes(&start), to_key_bytes(&last)]);
+                    if start > BigUint::from(0u8) {
+                        keys.push(to_key_bytes(&(start - 1u32)));
+                    }
+                    if end < BigUint::from(1u8) << (8 * PUBKEY_BYTES) {
+                        keys.push(to_key_bytes(&end));
+                    }
+                }
+            }
+            for key in keys {
+                let address = bs58::encode(key).into_string();
+                assert_eq!(
+                    filter.may_match(&key),
+                    !set.matches(&address).is_empty(),
+                    "{} {}",
+                    pattern,
+                    address
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn suffixes_of_small_keys_are_left_to_the_matcher() {
+        // A key below `58^3` encodes to padding `1`s followed by at most
+        // three digits, so `1z` can end it without its value ending in `1z`.
+        let set = PatternSet::new(&["1z", "z"], MatchPosition::Suffix, true);
+        let filter = KeyFilter::new(&["1z", "z"], MatchPosition::Suffix, true);
+        let mut rng = StdRng::seed_from_u64(9);
+        let mut keys = Vec::new();
+        for value in (0..58u64 * 58 * 58).step_by(7) {
+            let mut key = [0u8; PUBKEY_BYTES];
+            key[24..].copy_from_slice(&value.to_be_bytes());
+            keys.push(key);
+        }
+        keys.extend(random_keys(&mut rng, 2000));
+        check(&filter, &set, &keys);
+    }
+}
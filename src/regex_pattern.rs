@@ -0,0 +1,154 @@
+use crate::base58::ALPHABET;
+use regex::{RegexSet, RegexSetBuilder};
+use regex_automata::{
+    dfa::{dense, Automaton},
+    util::{start, syntax},
+    Anchored,
+};
+use std::collections::HashMap;
+
+/// Encoded lengths a Solana address realistically has. Shorter ones need
+/// several leading zero bytes and are too rare to search for.
+const ADDRESS_LENGTHS: [usize; 2] = [43, 44];
+
+/// Largest DFA built for validation and estimates; regexes needing more are
+/// accepted without either.
+const DFA_SIZE_LIMIT: usize = 16 * 1024 * 1024;
+
+/// Compiles every regex into one set that tests them all in a single pass.
+pub fn compile_set<S: AsRef<str>>(
+    patterns: &[S],
+    case_sensitive: bool,
+) -> Result<RegexSet, String> {
+    RegexSetBuilder::new(patterns.iter().map(AsRef::as_ref))
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// Checks that a regex compiles and can match a string of base58
+/// characters as long as an address.
+///
+/// Regexes too complex to turn into a DFA are let through, since there is
+/// no cheap way to tell whether they can match.
+pub fn validate(pattern: &str, case_sensitive: bool) -> Result<(), String> {
+    compile_set(&[pattern], case_sensitive)?;
+    let Some(dfa) = build_dfa(pattern, case_sensitive) else {
+        return Ok(());
+    };
+    let uniform = [1.0 / ALPHABET.len() as f64; 58];
+    if ADDRESS_LENGTHS
+        .iter()
+        .all(|&len| match_probability(&dfa, len, &uniform) == Some(0.0))
+    {
+        return Err(format!(
+            "regex `{}` cannot match any base58 address of {} or {} characters",
+            pattern, ADDRESS_LENGTHS[0], ADDRESS_LENGTHS[1]
+        ));
+    }
+    Ok(())
+}
+
+/// Chance that a random 44-character address matches `pattern`, given how
+/// likely each base58 character is to lead the address. The remaining
+/// characters are treated as uniform.
+///
+/// Returns `None` when the regex is too complex to turn into a DFA.
+pub fn probability(pattern: &str, case_sensitive: bool, leading: &[f64; 58]) -> Option<f64> {
+    let dfa = build_dfa(pattern, case_sensitive)?;
+    match_probability(&dfa, ADDRESS_LENGTHS[1], leading)
+}
+
+fn build_dfa(pattern: &str, case_sensitive: bool) -> Option<dense::DFA<Vec<u32>>> {
+    dense::Builder::new()
+        .configure(
+            dense::Config::new()
+                .dfa_size_limit(Some(DFA_SIZE_LIMIT))
+                .determinize_size_limit(Some(DFA_SIZE_LIMIT)),
+        )
+        .syntax(
+            syntax::Config::new()
+                .case_insensitive(!case_sensitive)
+                .unicode(false)
+                .utf8(false),
+        )
+        .build(pattern)
+        .ok()
+}
+
+/// Runs every string of `len` base58 characters through the DFA at once,
+/// tracking how much probability sits in each state, and returns the share
+/// that reached a match.
+fn match_probability(dfa: &dense::DFA<Vec<u32>>, len: usize, leading: &[f64; 58]) -> Option<f64> {
+    let uniform = 1.0 / ALPHABET.len() as f64;
+    let start = dfa
+        .start_state(&start::Config::new().anchored(Anchored::No))
+        .ok()?;
+
+    let mut matched = 0.0;
+    let mut states = HashMap::from([(start, 1.0)]);
+    for position in 0..len {
+        let mut next = HashMap::new();
+        for (state, weight) in states {
+            if dfa.is_match_state(state) {
+                matched += weight;
+                continue;
+            }
+            if dfa.is_dead_state(state) {
+                continue;
+            }
+            for (i, &c) in ALPHABET.iter().enumerate() {
+                let p = if position == 0 { leading[i] } else { uniform };
+                if p > 0.0 {
+                    *next.entry(dfa.next_state(state, c)).or_insert(0.0) += weight * p;
+                }
+            }
+        }
+        states = next;
+    }
+    for (state, weight) in states {
+        if dfa.is_match_state(state) || dfa.is_match_state(dfa.next_eoi_state(state)) {
+            matched += weight;
+        }
+    }
+    Some(matched.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{estimate::Difficulty, matcher::MatchPosition};
+
+    #[test]
+    fn regexes_that_need_missing_characters_are_rejected() {
+        assert!(validate("^0", true).is_err());
+        assert!(validate("[Il]", true).is_err());
+        assert!(validate("^Sol", true).is_err());
+        assert!(validate("x{45}", true).is_err());
+        assert!(validate("(", true).is_err());
+        assert!(validate("^SoL", true).is_ok());
+        assert!(validate("[Il]", false).is_ok());
+        assert!(validate("^.*[1-9]$", true).is_ok());
+    }
+
+    #[test]
+    fn anchored_regexes_are_estimated_like_prefixes() {
+        for (pattern, case_sensitive) in [("SoL", true), ("SoL", false), ("A", true), ("abc", true)]
+        {
+            let regex = Difficulty::for_regex(&format!("^{}", pattern), case_sensitive)
+                .unwrap()
+                .probability();
+            let prefix =
+                Difficulty::new(pattern, MatchPosition::Prefix, case_sensitive).probability();
+            assert!(
+                (regex / prefix - 1.0).abs() < 0.05,
+                "{} {} {}",
+                pattern,
+                regex,
+                prefix
+            );
+        }
+        let suffix = Difficulty::for_regex("xyz$", true).unwrap().probability();
+        assert!((suffix * 58f64.powi(3) - 1.0).abs() < 1e-9, "{}", suffix);
+    }
+}
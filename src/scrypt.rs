@@ -0,0 +1,101 @@
+//! The scrypt password-based key derivation function (RFC 7914).
+//!
+//! scrypt is memory-hard: deriving a key needs `128 * r * 2^log_n` bytes
+//! of memory, which makes guessing passwords on GPUs or ASICs expensive.
+//! The derivation itself is the `scrypt` crate's; this module holds the
+//! cost parameters keystores record, and the limits put on them.
+
+/// Cost parameters for [`scrypt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Params {
+    /// Base-2 logarithm of the CPU/memory cost `N`.
+    pub log_n: u8,
+    /// Block size.
+    pub r: u32,
+    /// Parallelization.
+    pub p: u32,
+}
+
+impl Params {
+    /// `N = 2^17, r = 8, p = 1`: 128 MiB and a fraction of a second per
+    /// derivation in a release build.
+    pub const RECOMMENDED: Params = Params {
+        log_n: 17,
+        r: 8,
+        p: 1,
+    };
+
+    /// Rejects parameters that are out of range or would need more than
+    /// 1 GiB of memory, e.g. when read from an untrusted file.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(1..=20).contains(&self.log_n) {
+            return Err(format!(
+                "scrypt log_n must be between 1 and 20, got {}",
+                self.log_n
+            ));
+        }
+        if self.r == 0 || self.p == 0 || self.r > 32 || self.p > 16 {
+            return Err(format!(
+                "scrypt r and p must be between 1 and 32 and 1 and 16, got {} and {}",
+                self.r, self.p
+            ));
+        }
+        if (128u64 * self.r as u64) << self.log_n > 1 << 30 {
+            return Err("scrypt parameters need more than 1 GiB of memory".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Derives `out.len()` bytes from `password` and `salt`, with the
+/// `scrypt` crate.
+///
+/// Panics if `params` fail [`Params::validate`] or `out` is not between 10
+/// and 64 bytes long.
+pub fn scrypt(password: &[u8], salt: &[u8], params: Params, out: &mut [u8]) {
+    params.validate().expect("invalid scrypt parameters");
+    let params = ::scrypt::Params::new(params.log_n, params.r, params.p, out.len())
+        .expect("validated scrypt parameters");
+    ::scrypt::scrypt(password, salt, &params, out)
+        .expect("output length checked by the parameters");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn derive(password: &str, salt: &str, log_n: u8, r: u32, p: u32) -> [u8; 64] {
+        let mut out = [0u8; 64];
+        scrypt(
+            password.as_bytes(),
+            salt.as_bytes(),
+            Params { log_n, r, p },
+            &mut out,
+        );
+        out
+    }
+
+    #[test]
+    fn matches_rfc_7914_test_vectors() {
+        assert_eq!(
+            derive("", "", 4, 1, 1),
+            [
+                0x77, 0xd6, 0x57, 0x62, 0x38, 0x65, 0x7b, 0x20, 0x3b, 0x19, 0xca, 0x42, 0xc1, 0x8a,
+                0x04, 0x97, 0xf1, 0x6b, 0x48, 0x44, 0xe3, 0x07, 0x4a, 0xe8, 0xdf, 0xdf, 0xfa, 0x3f,
+                0xed, 0xe2, 0x14, 0x42, 0xfc, 0xd0, 0x06, 0x9d, 0xed, 0x09, 0x48, 0xf8, 0x32, 0x6a,
+                0x75, 0x3a, 0x0f, 0xc8, 0x1f, 0x17, 0xe8, 0xd3, 0xe0, 0xfb, 0x2e, 0x0d, 0x36, 0x28,
+                0xcf, 0x35, 0xe2, 0x0c, 0x38, 0xd1, 0x89, 0x06,
+            ]
+        );
+        assert_eq!(
+            derive("password", "NaCl", 10, 8, 16),
+            [
+                0xfd, 0xba, 0xbe, 0x1c, 0x9d, 0x34, 0x72, 0x00, 0x78, 0x56, 0xe7, 0x19, 0x0d, 0x01,
+                0xe9, 0xfe, 0x7c, 0x6a, 0xd7, 0xcb, 0xc8, 0x23, 0x78, 0x30, 0xe7, 0x73, 0x76, 0x63,
+                0x4b, 0x37, 0x31, 0x62, 0x2e, 0xaf, 0x30, 0xd9, 0x2e, 0x22, 0xa3, 0x88, 0x6f, 0xf1,
+                0x09, 0x27, 0x9d, 0x98, 0x30, 0xda, 0xc7, 0x27, 0xaf, 0xb9, 0x4a, 0x83, 0xee, 0x6d,
+                0x83, 0x60, 0xcb, 0xdf, 0xa2, 0xcc, 0x06, 0x40,
+            ]
+        );
+    }
+}
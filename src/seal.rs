@@ -0,0 +1,316 @@
+//! Public-key encryption of results, in the style of `age`.
+//!
+//! A record is encrypted once under a random file key, and the file key is
+//! wrapped for each recipient's X25519 public key: an ephemeral key pair is
+//! generated, the Diffie-Hellman secret is run through HKDF-SHA256, and the
+//! result encrypts the file key. Only the holder of a recipient's identity
+//! can unwrap it, so the machine doing the search never has anything it
+//! could decrypt itself.
+//!
+//! Each sealed record is one line of JSON; the address stays in the clear
+//! so records can be told apart, and is authenticated with the ciphertext.
+//!
+//! ```json
+//! {"version":1,"pubkey":"...","recipients":[{"ephemeral":"...","wrapped_key":"..."}],"nonce":"...","ciphertext":"..."}
+//! ```
+
+use aes_gcm_siv::{
+    aead::{Aead, NewAead, Payload},
+    Aes256GcmSiv, Nonce,
+};
+use curve25519_dalek::MontgomeryPoint;
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use solana_sdk::bs58;
+use std::{fmt, str::FromStr};
+use zeroize::{Zeroize, Zeroizing};
+
+const VERSION: u64 = 1;
+const RECIPIENT_PREFIX: &str = "vanaddy-x25519:";
+const IDENTITY_PREFIX: &str = "VANADDY-X25519-SECRET:";
+const NONCE_LEN: usize = 12;
+
+/// An X25519 private key that can open records sealed to its
+/// [`Recipient`]. Wiped when dropped.
+#[derive(Clone)]
+pub struct Identity {
+    secret: [u8; 32],
+}
+
+/// An X25519 public key results can be sealed to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Recipient {
+    public: MontgomeryPoint,
+}
+
+impl Identity {
+    pub fn generate() -> Self {
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        Identity { secret }
+    }
+
+    pub fn recipient(&self) -> Recipient {
+        Recipient {
+            public: MontgomeryPoint::mul_base_clamped(self.secret),
+        }
+    }
+
+    /// Reads an identity file: the first line starting with the identity
+    /// prefix, ignoring `#` comments and blank lines.
+    pub fn from_file_contents(contents: &str) -> Result<Self, String> {
+        contents
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with(IDENTITY_PREFIX))
+            .ok_or_else(|| "no identity found".to_string())?
+            .parse()
+    }
+}
+
+impl Drop for Identity {
+    fn drop(&mut self) {
+        self.secret.zeroize();
+    }
+}
+
+impl fmt::Display for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            IDENTITY_PREFIX,
+            bs58::encode(self.secret).into_string()
+        )
+    }
+}
+
+impl fmt::Debug for Identity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Identity")
+            .field("recipient", &self.recipient())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromStr for Identity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let key = s
+            .strip_prefix(IDENTITY_PREFIX)
+            .ok_or_else(|| format!("identity must start with `{}`", IDENTITY_PREFIX))?;
+        Ok(Identity {
+            secret: decode_key(key)?,
+        })
+    }
+}
+
+impl fmt::Display for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            RECIPIENT_PREFIX,
+            bs58::encode(self.public.as_bytes()).into_string()
+        )
+    }
+}
+
+impl FromStr for Recipient {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let key = s
+            .strip_prefix(RECIPIENT_PREFIX)
+            .ok_or_else(|| format!("recipient must start with `{}`", RECIPIENT_PREFIX))?;
+        let public = MontgomeryPoint(decode_key(key)?);
+        // Clamped scalars are multiples of the cofactor, so a low-order
+        // point would give every sender the same all-zero shared secret.
+        if public.mul_clamped([0xff; 32]) == MontgomeryPoint([0; 32]) {
+            return Err("recipient is not a usable public key".to_string());
+        }
+        Ok(Recipient { public })
+    }
+}
+
+fn decode_key(key: &str) -> Result<[u8; 32], String> {
+    let bytes = bs58::decode(key)
+        .into_vec()
+        .map_err(|e| format!("invalid base58 key: {}", e))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("key must be 32 bytes, got {}", bytes.len()))
+}
+
+/// Encrypts `plaintext` about `public_key` so any of `recipients` can open
+/// it, returning the sealed record as one line of JSON.
+///
+/// Panics if `recipients` is empty.
+pub fn seal(public_key: &str, plaintext: &[u8], recipients: &[Recipient]) -> String {
+    assert!(!recipients.is_empty(), "sealing needs a recipient");
+    let mut file_key = Zeroizing::new([0u8; 32]);
+    let mut nonce = [0u8; NONCE_LEN];
+    OsRng.fill_bytes(&mut *file_key);
+    OsRng.fill_bytes(&mut nonce);
+
+    let stanzas: Vec<Value> = recipients
+        .iter()
+        .map(|recipient| {
+            let ephemeral = Identity::generate();
+            let ephemeral_public = ephemeral.recipient().public;
+            let shared = recipient.public.mul_clamped(ephemeral.secret);
+            let wrapped = wrapping_cipher(&shared, &ephemeral_public, &recipient.public)
+                .encrypt(&Nonce::from([0; NONCE_LEN]), file_key.as_slice())
+                .expect("AES-GCM-SIV encrypts any message this small");
+            json!({
+                "ephemeral": bs58::encode(ephemeral_public.as_bytes()).into_string(),
+                "wrapped_key": bs58::encode(wrapped).into_string(),
+            })
+        })
+        .collect();
+
+    let ciphertext = Aes256GcmSiv::new_from_slice(&*file_key)
+        .expect("AES-256 takes a 32-byte key")
+        .encrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: plaintext,
+                aad: &associated_data(public_key),
+            },
+        )
+        .expect("AES-GCM-SIV encrypts any message this small");
+
+    json!({
+        "version": VERSION,
+        "pubkey": public_key,
+        "recipients": stanzas,
+        "nonce": bs58::encode(nonce).into_string(),
+        "ciphertext": bs58::encode(ciphertext).into_string(),
+    })
+    .to_string()
+}
+
+/// Opens a sealed record with `identity`, returning the address it is
+/// about and the plaintext.
+pub fn open(record: &str, identity: &Identity) -> Result<(String, Zeroizing<Vec<u8>>), String> {
+    let record: Value =
+        serde_json::from_str(record).map_err(|e| format!("not a sealed record: {}", e))?;
+    if record["version"].as_u64() != Some(VERSION) {
+        return Err(format!("unsupported record version {}", record["version"]));
+    }
+    let public_key = string_field(&record["pubkey"], "pubkey")?;
+    let stanzas = record["recipients"]
+        .as_array()
+        .ok_or("record field `recipients` is missing")?;
+
+    let recipient = identity.recipient().public;
+    let file_key = stanzas
+        .iter()
+        .find_map(|stanza| {
+            let ephemeral = MontgomeryPoint(
+                base58_field(&stanza["ephemeral"], "ephemeral")
+                    .ok()?
+                    .try_into()
+                    .ok()?,
+            );
+            let wrapped = base58_field(&stanza["wrapped_key"], "wrapped_key").ok()?;
+            let shared = ephemeral.mul_clamped(identity.secret);
+            wrapping_cipher(&shared, &ephemeral, &recipient)
+                .decrypt(&Nonce::from([0; NONCE_LEN]), wrapped.as_slice())
+                .ok()
+                .map(Zeroizing::new)
+        })
+        .ok_or("record is not sealed to this identity")?;
+    let cipher = Aes256GcmSiv::new_from_slice(&file_key)
+        .map_err(|_| "wrapped key is not 32 bytes".to_string())?;
+
+    let nonce: [u8; NONCE_LEN] = base58_field(&record["nonce"], "nonce")?
+        .try_into()
+        .map_err(|nonce: Vec<u8>| {
+            format!("nonce must be {} bytes, got {}", NONCE_LEN, nonce.len())
+        })?;
+    let ciphertext = base58_field(&record["ciphertext"], "ciphertext")?;
+    let plaintext = cipher
+        .decrypt(
+            &Nonce::from(nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: &associated_data(public_key),
+            },
+        )
+        .map_err(|_| "corrupted record".to_string())?;
+    Ok((public_key.to_owned(), Zeroizing::new(plaintext)))
+}
+
+/// The cipher that wraps the file key for one recipient, keyed by
+/// HKDF-SHA256 over the Diffie-Hellman secret and both public keys.
+fn wrapping_cipher(
+    shared: &MontgomeryPoint,
+    ephemeral: &MontgomeryPoint,
+    recipient: &MontgomeryPoint,
+) -> Aes256GcmSiv {
+    let mut salt = [0u8; 64];
+    salt[..32].copy_from_slice(ephemeral.as_bytes());
+    salt[32..].copy_from_slice(recipient.as_bytes());
+    let mut key = Zeroizing::new([0u8; 32]);
+    Hkdf::<Sha256>::new(Some(&salt), shared.as_bytes())
+        .expand(b"vanaddy-x25519", &mut *key)
+        .expect("HKDF-SHA256 gives 32 bytes");
+    Aes256GcmSiv::new_from_slice(&*key).expect("AES-256 takes a 32-byte key")
+}
+
+fn associated_data(public_key: &str) -> Vec<u8> {
+    format!("vanaddy-sealed-v{}:{}", VERSION, public_key).into_bytes()
+}
+
+fn string_field<'a>(value: &'a Value, name: &str) -> Result<&'a str, String> {
+    value
+        .as_str()
+        .ok_or_else(|| format!("record field `{}` is missing or not a string", name))
+}
+
+fn base58_field(value: &Value, name: &str) -> Result<Vec<u8>, String> {
+    bs58::decode(string_field(value, name)?)
+        .into_vec()
+        .map_err(|e| format!("record field `{}` is not base58: {}", name, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_recipient_can_open_and_no_one_else() {
+        let alice = Identity::generate();
+        let bob = Identity::generate();
+        let mallory = Identity::generate();
+        let record = seal("Sol111", b"secret", &[alice.recipient(), bob.recipient()]);
+
+        for identity in [&alice, &bob] {
+            let (public_key, plaintext) = open(&record, identity).unwrap();
+            assert_eq!(public_key, "Sol111");
+            assert_eq!(*plaintext, b"secret");
+        }
+        assert!(open(&record, &mallory).is_err());
+        assert!(open(&record.replace("Sol111", "Sol222"), &alice).is_err());
+    }
+
+    #[test]
+    fn keys_round_trip_through_text() {
+        let identity = Identity::generate();
+        let file = format!("# public key: {}\n{}\n", identity.recipient(), identity);
+        let restored = Identity::from_file_contents(&file).unwrap();
+        assert_eq!(restored.recipient(), identity.recipient());
+        assert_eq!(
+            identity
+                .recipient()
+                .to_string()
+                .parse::<Recipient>()
+                .unwrap(),
+            identity.recipient()
+        );
+    }
+}
@@ -0,0 +1,799 @@
+use crate::{
+    base58::{self, PUBKEY_BYTES},
+    cli,
+    expanded_key::ExpandedKeypair,
+    keystore::Password,
+    matcher::{MatchPosition, PatternSet},
+    mnemonic::{self, AccountRoot, DerivationPath, PhraseSettings, SeedPhrase, WordCount},
+    output::{Output, WriteMode},
+    pda::{PdaSettings, ProgramAddress},
+    regex_pattern,
+    secret::SecretKey,
+    split_key::{Offset, PartialKey, SplitMatch},
+    state::SearchState,
+    walk::ScalarWalk,
+    with_seed::{AccountSeed, WithSeedSettings},
+};
+use clap::ValueEnum;
+use solana_sdk::{
+    bs58,
+    signature::{Keypair, Signer},
+};
+use std::{
+    net::SocketAddr,
+    ops::RangeInclusive,
+    sync::{mpsc, Arc},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+pub const BATCH_SIZE: usize = 1000;
+
+/// Seed phrases take a couple of thousand hashes each, so mnemonic workers
+/// report progress and check for the end of the search more often: after
+/// at most this many phrases, or fewer when each has many accounts.
+pub const MNEMONIC_BATCH_SIZE: u64 = 16;
+
+/// One vanity string and how many wallets to find for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub pattern: String,
+    pub count: u64,
+}
+
+/// A matching keypair, or derived address, on its way from a worker to the
+/// results file.
+#[derive(Debug, Clone)]
+pub struct FoundKey {
+    pub public_key: String,
+    /// `None` for program addresses, addresses created with a seed and
+    /// split-key matches, whose private key the searcher can't know.
+    pub secret_key: Option<SecretKey>,
+    /// The vanity string the address matched.
+    pub pattern: String,
+    /// Index of the worker thread that found the key.
+    pub worker: usize,
+    /// Keys the whole search had generated when the key was found, counted
+    /// in completed batches.
+    pub attempts: u64,
+    pub found_at: SystemTime,
+    /// Whether `secret_key` is an [`ExpandedKeypair`] rather than a
+    /// Solana keypair.
+    pub expanded: bool,
+    /// The phrase the keypair was derived from, for mnemonic searches.
+    pub seed_phrase: Option<SeedPhrase>,
+    /// How to derive the address, for program address searches.
+    pub program_address: Option<ProgramAddress>,
+    /// How to create the account, for `create_with_seed` searches.
+    pub account_seed: Option<AccountSeed>,
+    /// The offset to combine with the requester's secret, for split-key
+    /// searches.
+    pub split_key: Option<SplitMatch>,
+}
+
+impl FoundKey {
+    /// Copies out the secret key of a confirmed match. This is the only
+    /// place it leaves the `Keypair`.
+    pub fn new(
+        keypair: &Keypair,
+        public_key: String,
+        pattern: &str,
+        worker: usize,
+        attempts: u64,
+    ) -> Self {
+        FoundKey {
+            secret_key: Some(SecretKey::from_keypair(keypair)),
+            ..FoundKey::matched(public_key, pattern, worker, attempts)
+        }
+    }
+
+    /// Records the seed phrase `keypair` was derived from.
+    pub fn with_seed_phrase(mut self, seed_phrase: SeedPhrase) -> Self {
+        self.seed_phrase = Some(seed_phrase);
+        self
+    }
+
+    /// Copies out the secret key of a confirmed match from a scalar walk.
+    /// The secret key is the 64-byte expanded key, not a Solana keypair.
+    pub fn expanded(
+        keypair: &ExpandedKeypair,
+        public_key: String,
+        pattern: &str,
+        worker: usize,
+        attempts: u64,
+    ) -> Self {
+        FoundKey {
+            secret_key: Some(SecretKey::from_expanded(keypair)),
+            expanded: true,
+            ..FoundKey::matched(public_key, pattern, worker, attempts)
+        }
+    }
+
+    /// A matching program address, with the seeds and bump that derive it.
+    pub fn program_address(
+        program_address: ProgramAddress,
+        public_key: String,
+        pattern: &str,
+        worker: usize,
+        attempts: u64,
+    ) -> Self {
+        FoundKey {
+            program_address: Some(program_address),
+            ..FoundKey::matched(public_key, pattern, worker, attempts)
+        }
+    }
+
+    /// A matching address created with a seed, with the base, seed and
+    /// owner that derive it.
+    pub fn account_seed(
+        account_seed: AccountSeed,
+        public_key: String,
+        pattern: &str,
+        worker: usize,
+        attempts: u64,
+    ) -> Self {
+        FoundKey {
+            account_seed: Some(account_seed),
+            ..FoundKey::matched(public_key, pattern, worker, attempts)
+        }
+    }
+
+    /// A matching address for someone else's partial key, with the offset
+    /// that leads to it.
+    pub fn split_key(
+        split_key: SplitMatch,
+        public_key: String,
+        pattern: &str,
+        worker: usize,
+        attempts: u64,
+    ) -> Self {
+        FoundKey {
+            split_key: Some(split_key),
+            ..FoundKey::matched(public_key, pattern, worker, attempts)
+        }
+    }
+
+    /// A match found now, with nothing yet saying how to derive it.
+    fn matched(public_key: String, pattern: &str, worker: usize, attempts: u64) -> Self {
+        FoundKey {
+            public_key,
+            secret_key: None,
+            pattern: pattern.to_owned(),
+            worker,
+            attempts,
+            found_at: SystemTime::now(),
+            expanded: false,
+            seed_phrase: None,
+            program_address: None,
+            account_seed: None,
+            split_key: None,
+        }
+    }
+}
+
+/// How workers produce candidate keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Generator {
+    /// A fresh Solana keypair per candidate
+    Keypair,
+    /// Consecutive scalars from one random start; several times faster, but
+    /// results are expanded ed25519 keys rather than Solana keypairs
+    ScalarWalk,
+    /// A BIP39 seed phrase per candidate, derived along `m/44'/501'/0'/0'`
+    /// like wallets do; importable anywhere, but thousands of times slower
+    Mnemonic,
+    /// Program derived addresses for `--program-id` and `--seed`; no
+    /// private keys
+    Pda,
+    /// `create_with_seed` addresses of `--base` for `--owner`, with seeds
+    /// from `--seed-template`; no private keys
+    WithSeed,
+    /// Offsets from someone else's `--partial-key`, walked like
+    /// scalar-walk; only the holder of its secret can use the matches
+    SplitKey,
+}
+
+/// A [`Generator`] with everything its workers need.
+#[derive(Debug, Clone)]
+pub enum KeySource {
+    Keypair,
+    ScalarWalk,
+    Mnemonic(PhraseSettings),
+    Pda(PdaSettings),
+    WithSeed(WithSeedSettings),
+    SplitKey(PartialKey),
+}
+
+/// Everything needed to run one search, whether it came from the command
+/// line or from the interactive prompts.
+#[derive(Debug, Clone)]
+pub struct SearchConfig {
+    pub targets: Vec<Target>,
+    pub case_sensitive: bool,
+    pub position: MatchPosition,
+    /// Treat the patterns as regular expressions instead of literal
+    /// strings at `position`.
+    pub regex: bool,
+    pub generator: Generator,
+    /// Length of the seed phrases of a mnemonic search.
+    pub word_count: WordCount,
+    /// Ask for a BIP39 passphrase to derive the keys of a mnemonic search.
+    pub passphrase: bool,
+    /// Accounts checked for each seed phrase of a mnemonic search.
+    pub accounts: RangeInclusive<u32>,
+    /// The program and seeds of a program address search.
+    pub program_address: Option<PdaSettings>,
+    /// The base, owner and seed template of a `create_with_seed` search.
+    pub with_seed: Option<WithSeedSettings>,
+    /// The requester's partial key of a split-key search.
+    pub partial_key: Option<PartialKey>,
+    /// Local worker threads; zero for a coordinator that only hands out
+    /// work.
+    pub max_threads: usize,
+    /// Also hand the search out to worker processes connecting here.
+    pub listen: Option<SocketAddr>,
+    /// Everywhere matches are written to.
+    pub outputs: Vec<Output>,
+    pub write_mode: WriteMode,
+    pub time_limit: Option<Duration>,
+    /// Lock the process's memory so secrets can't be swapped to disk.
+    pub lock_memory: bool,
+}
+
+impl SearchConfig {
+    /// A search for `targets` with `generator` and everything else at its
+    /// default: case-sensitive prefixes on every core, with no outputs.
+    pub fn new(targets: Vec<Target>, generator: Generator) -> Self {
+        SearchConfig {
+            targets,
+            case_sensitive: true,
+            position: MatchPosition::Prefix,
+            regex: false,
+            generator,
+            word_count: WordCount::Twelve,
+            passphrase: false,
+            accounts: 0..=0,
+            program_address: None,
+            with_seed: None,
+            partial_key: None,
+            max_threads: cli::default_thread_count(),
+            listen: None,
+            outputs: Vec::new(),
+            write_mode: WriteMode::Create,
+            time_limit: None,
+            lock_memory: false,
+        }
+    }
+
+    /// Rejects patterns that no Solana address can match, so the workers
+    /// are never started on a search that would spin forever.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.targets.is_empty() {
+            return Err("no vanity strings given".to_string());
+        }
+        if self.outputs.is_empty() {
+            return Err("no outputs given".to_string());
+        }
+        if self.generator == Generator::ScalarWalk
+            && self
+                .outputs
+                .iter()
+                .any(|output| matches!(output, Output::KeypairDir(_)))
+        {
+            return Err(
+                "scalar-walk finds expanded keys, which can't be written as keypair files"
+                    .to_string(),
+            );
+        }
+        let derived = match self.generator {
+            Generator::Pda => {
+                self.program_address
+                    .as_ref()
+                    .ok_or("a program address search needs `--program-id`")?
+                    .validate()?;
+                Some("program addresses")
+            }
+            Generator::WithSeed => {
+                self.with_seed
+                    .as_ref()
+                    .ok_or("a `create_with_seed` search needs `--base` and `--owner`")?
+                    .validate()?;
+                Some("addresses created with a seed")
+            }
+            Generator::SplitKey => {
+                self.partial_key
+                    .ok_or("a split-key search needs `--partial-key`")?;
+                Some("split-key matches")
+            }
+            _ => None,
+        };
+        if let Some(derived) = derived {
+            if let Some(output) = self.outputs.iter().find(|output| {
+                !matches!(
+                    output,
+                    Output::Csv(_) | Output::JsonLines(_) | Output::Stdout
+                )
+            }) {
+                return Err(format!(
+                    "{} have no private key to write to {}; use csv, jsonl or stdout",
+                    derived, output
+                ));
+            }
+        }
+        if self.max_threads == 0 && self.listen.is_none() {
+            return Err("a search needs at least one thread".to_string());
+        }
+        if self.listen.is_some()
+            && !matches!(
+                self.generator,
+                Generator::Keypair | Generator::ScalarWalk | Generator::SplitKey
+            )
+        {
+            return Err(
+                "distributed searches support the keypair, scalar-walk and split-key generators"
+                    .to_string(),
+            );
+        }
+        self.validate_patterns()
+    }
+
+    /// Checks that every pattern can match some address.
+    pub fn validate_patterns(&self) -> Result<(), String> {
+        for target in &self.targets {
+            let result = if self.regex {
+                regex_pattern::validate(&target.pattern, self.case_sensitive)
+            } else {
+                cli::validate_vanity_string(&target.pattern).and_then(|()| {
+                    base58::validate_pattern(&target.pattern, self.position, self.case_sensitive)
+                })
+            };
+            result.map_err(|e| {
+                if self.targets.len() > 1 {
+                    format!("pattern `{}`: {}", target.pattern, e)
+                } else {
+                    e
+                }
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Compiles the patterns for matching.
+    ///
+    /// Panics if the config holds a regex that `validate` would reject.
+    pub fn patterns(&self) -> PatternSet {
+        let patterns: Vec<&str> = self.targets.iter().map(|t| t.pattern.as_str()).collect();
+        if self.regex {
+            PatternSet::regex(&patterns, self.case_sensitive).expect("regexes were validated")
+        } else {
+            PatternSet::new(&patterns, self.position, self.case_sensitive)
+        }
+    }
+
+    /// How patterns are matched and keys generated, as recorded in the
+    /// results file.
+    pub fn mode(&self) -> String {
+        let matching = if self.regex {
+            "regex"
+        } else {
+            self.position.as_str()
+        };
+        match self.generator {
+            Generator::Keypair => matching.to_owned(),
+            Generator::ScalarWalk => format!("{} (expanded key)", matching),
+            Generator::Mnemonic => format!("{} (seed phrase)", matching),
+            Generator::Pda => format!("{} (program address)", matching),
+            Generator::WithSeed => format!("{} (with seed)", matching),
+            Generator::SplitKey => format!("{} (split key)", matching),
+        }
+    }
+
+    /// Where workers get their keys from. `passphrase` is the BIP39
+    /// passphrase of a mnemonic search, if [`passphrase`](Self::passphrase)
+    /// asked for one.
+    pub fn key_source(&self, passphrase: Option<Password>) -> KeySource {
+        match self.generator {
+            Generator::Keypair => KeySource::Keypair,
+            Generator::ScalarWalk => KeySource::ScalarWalk,
+            Generator::Mnemonic => KeySource::Mnemonic(PhraseSettings {
+                word_count: self.word_count,
+                passphrase: passphrase.unwrap_or_else(|| Password::new(String::new())),
+                accounts: self.accounts.clone(),
+            }),
+            Generator::Pda => KeySource::Pda(
+                self.program_address
+                    .clone()
+                    .expect("program address searches have settings"),
+            ),
+            Generator::WithSeed => KeySource::WithSeed(
+                self.with_seed
+                    .clone()
+                    .expect("`create_with_seed` searches have settings"),
+            ),
+            Generator::SplitKey => KeySource::SplitKey(
+                self.partial_key
+                    .expect("split-key searches have a partial key"),
+            ),
+        }
+    }
+
+    /// Wallets to find for each pattern, in pattern order.
+    pub fn counts(&self) -> Vec<u64> {
+        self.targets.iter().map(|t| t.count).collect()
+    }
+
+    /// Wallets to find across all patterns.
+    pub fn wallet_count_target(&self) -> u64 {
+        self.targets.iter().map(|t| t.count).sum()
+    }
+}
+
+/// Checks a public key against the patterns, returning its address and the
+/// indexes of the patterns it matches.
+pub fn check_public_key(
+    public_key: &[u8; PUBKEY_BYTES],
+    patterns: &PatternSet,
+) -> Option<(String, Vec<usize>)> {
+    // Most keys are ruled out from their raw bytes, so only the rest pay
+    // for encoding the address.
+    if !patterns.may_match(public_key) {
+        return None;
+    }
+    let address = bs58::encode(public_key).into_string();
+    let hits = patterns.matches(&address);
+    (!hits.is_empty()).then_some((address, hits))
+}
+
+/// Starts one worker per counter in `state`, each generating keys and
+/// sending matches to `tx` until `state` says the search is finished.
+pub fn spawn_threads(
+    source: KeySource,
+    patterns: PatternSet,
+    state: Arc<SearchState>,
+    tx: mpsc::Sender<FoundKey>,
+) -> Vec<thread::JoinHandle<()>> {
+    (0..state.workers())
+        .map(|worker| {
+            let source = source.clone();
+            let patterns = patterns.clone();
+            let state = Arc::clone(&state);
+            let tx = tx.clone();
+
+            thread::spawn(move || match &source {
+                KeySource::Keypair => keypair_worker(worker, &patterns, &state, &tx),
+                KeySource::ScalarWalk => scalar_walk_worker(worker, &patterns, &state, &tx),
+                KeySource::Mnemonic(settings) => {
+                    mnemonic_worker(worker, settings, &patterns, &state, &tx)
+                }
+                KeySource::Pda(settings) => pda_worker(worker, settings, &patterns, &state, &tx),
+                KeySource::WithSeed(settings) => {
+                    with_seed_worker(worker, settings, &patterns, &state, &tx)
+                }
+                KeySource::SplitKey(partial_key) => {
+                    split_key_worker(worker, partial_key, &patterns, &state, &tx)
+                }
+            })
+        })
+        .collect()
+}
+
+/// Hands a match to the writer. Once the writer is gone, for example
+/// after a server job is cancelled, nobody wants more, so the search stops.
+fn send(tx: &mpsc::Sender<FoundKey>, state: &SearchState, found: FoundKey) {
+    if tx.send(found).is_err() {
+        state.stop();
+    }
+}
+
+fn keypair_worker(
+    worker: usize,
+    patterns: &PatternSet,
+    state: &SearchState,
+    tx: &mpsc::Sender<FoundKey>,
+) {
+    while !state.is_finished() {
+        // Generate keypairs in batches
+        for _ in 0..BATCH_SIZE {
+            // `Keypair` wipes its secret key when dropped, so candidates
+            // that don't match leave nothing behind.
+            let keypair = Keypair::new();
+            let Some((public_key, hits)) = check_public_key(&keypair.pubkey().to_bytes(), patterns)
+            else {
+                continue;
+            };
+
+            // Hand the match to the first pattern that still needs one
+            if let Some(index) = state.claim(&hits) {
+                let found = FoundKey::new(
+                    &keypair,
+                    public_key,
+                    patterns.pattern(index),
+                    worker,
+                    state.generated(),
+                );
+                send(tx, state, found);
+            }
+        }
+        state.add_generated(worker, BATCH_SIZE as u64);
+    }
+}
+
+fn scalar_walk_worker(
+    worker: usize,
+    patterns: &PatternSet,
+    state: &SearchState,
+    tx: &mpsc::Sender<FoundKey>,
+) {
+    let mut walk = ScalarWalk::new(BATCH_SIZE);
+    while !state.is_finished() {
+        walk.next_batch();
+        for (i, key) in walk.keys().iter().enumerate() {
+            let Some((public_key, hits)) = check_public_key(key, patterns) else {
+                continue;
+            };
+            if let Some(index) = state.claim(&hits) {
+                let keypair = ExpandedKeypair::from_scalar(walk.scalar(i));
+                let found = FoundKey::expanded(
+                    &keypair,
+                    public_key,
+                    patterns.pattern(index),
+                    worker,
+                    state.generated(),
+                );
+                send(tx, state, found);
+            }
+        }
+        state.add_generated(worker, BATCH_SIZE as u64);
+    }
+}
+
+fn split_key_worker(
+    worker: usize,
+    partial_key: &PartialKey,
+    patterns: &PatternSet,
+    state: &SearchState,
+    tx: &mpsc::Sender<FoundKey>,
+) {
+    let mut walk = ScalarWalk::from_partial(partial_key.point(), BATCH_SIZE);
+    while !state.is_finished() {
+        walk.next_batch();
+        for (i, key) in walk.keys().iter().enumerate() {
+            let Some((public_key, hits)) = check_public_key(key, patterns) else {
+                continue;
+            };
+            if let Some(index) = state.claim(&hits) {
+                let split_key = SplitMatch {
+                    partial_key: *partial_key,
+                    offset: Offset::new(walk.scalar(i)),
+                };
+                let found = FoundKey::split_key(
+                    split_key,
+                    public_key,
+                    patterns.pattern(index),
+                    worker,
+                    state.generated(),
+                );
+                send(tx, state, found);
+            }
+        }
+        state.add_generated(worker, BATCH_SIZE as u64);
+    }
+}
+
+fn mnemonic_worker(
+    worker: usize,
+    settings: &PhraseSettings,
+    patterns: &PatternSet,
+    state: &SearchState,
+    tx: &mpsc::Sender<FoundKey>,
+) {
+    let accounts = u64::from(settings.accounts.end() - settings.accounts.start()) + 1;
+    let phrases_per_batch = (BATCH_SIZE as u64 / accounts).clamp(1, MNEMONIC_BATCH_SIZE);
+    while !state.is_finished() {
+        for _ in 0..phrases_per_batch {
+            // The phrase, its root and every keypair wipe themselves when
+            // dropped.
+            let phrase = mnemonic::generate(settings.word_count);
+            let root = AccountRoot::new(&phrase, &settings.passphrase);
+            for account in settings.accounts.clone() {
+                let keypair = root.account(account);
+                let Some((public_key, hits)) =
+                    check_public_key(&keypair.pubkey().to_bytes(), patterns)
+                else {
+                    continue;
+                };
+                if let Some(index) = state.claim(&hits) {
+                    let path = DerivationPath::solana(account);
+                    let found = FoundKey::new(
+                        &keypair,
+                        public_key,
+                        patterns.pattern(index),
+                        worker,
+                        state.generated(),
+                    )
+                    .with_seed_phrase(mnemonic::seed_phrase(&phrase, &path));
+                    send(tx, state, found);
+                }
+            }
+        }
+        state.add_seeds(phrases_per_batch);
+        state.add_generated(worker, phrases_per_batch * accounts);
+    }
+}
+
+fn pda_worker(
+    worker: usize,
+    settings: &PdaSettings,
+    patterns: &PatternSet,
+    state: &SearchState,
+    tx: &mpsc::Sender<FoundKey>,
+) {
+    // Workers take turns with counters, so none checks the same seed twice
+    let stride = state.workers() as u64;
+    let mut counter = worker as u64;
+    let mut rng = rand::thread_rng();
+    while !state.is_finished() {
+        for _ in 0..BATCH_SIZE {
+            let candidate = settings.candidate(counter, &mut rng);
+            counter += stride;
+            let Some((address, bump)) = settings.find(&candidate) else {
+                continue;
+            };
+            let Some((public_key, hits)) = check_public_key(&address.to_bytes(), patterns) else {
+                continue;
+            };
+            if let Some(index) = state.claim(&hits) {
+                let program_address = ProgramAddress {
+                    program_id: settings.program_id,
+                    seeds: settings.seeds_with(&candidate),
+                    bump,
+                };
+                let found = FoundKey::program_address(
+                    program_address,
+                    public_key,
+                    patterns.pattern(index),
+                    worker,
+                    state.generated(),
+                );
+                send(tx, state, found);
+            }
+        }
+        state.add_generated(worker, BATCH_SIZE as u64);
+    }
+}
+
+fn with_seed_worker(
+    worker: usize,
+    settings: &WithSeedSettings,
+    patterns: &PatternSet,
+    state: &SearchState,
+    tx: &mpsc::Sender<FoundKey>,
+) {
+    let mut seeds = settings.seeds();
+    let mut rng = rand::thread_rng();
+    while !state.is_finished() {
+        for _ in 0..BATCH_SIZE {
+            let address = settings.address(seeds.next(&mut rng));
+            let Some((public_key, hits)) = check_public_key(&address.to_bytes(), patterns) else {
+                continue;
+            };
+            if let Some(index) = state.claim(&hits) {
+                let account_seed = AccountSeed {
+                    base: settings.base,
+                    seed: seeds.as_str().to_owned(),
+                    owner: settings.owner,
+                };
+                let found = FoundKey::account_seed(
+                    account_seed,
+                    public_key,
+                    patterns.pattern(index),
+                    worker,
+                    state.generated(),
+                );
+                send(tx, state, found);
+            }
+        }
+        state.add_generated(worker, BATCH_SIZE as u64);
+    }
+}
+
+/// Keys and seed phrases per second.
+#[derive(Debug, Clone, Copy)]
+pub struct Throughput {
+    pub keys: f64,
+    /// Zero unless the keys come from seed phrases.
+    pub seeds: f64,
+}
+
+/// Measures how many keys per second `spawn_threads` gets through on this
+/// machine by running it for `duration` and throwing the results away.
+pub fn measure_throughput(
+    source: KeySource,
+    max_threads: usize,
+    patterns: PatternSet,
+    duration: Duration,
+) -> Throughput {
+    let state = Arc::new(SearchState::new(
+        vec![u64::MAX; patterns.len()],
+        max_threads,
+    ));
+    let (tx, rx) = mpsc::channel();
+
+    let start_time = Instant::now();
+    let handles = spawn_threads(source, patterns, state.clone(), tx);
+    thread::sleep(duration);
+    state.stop();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    drop(rx);
+
+    let elapsed = start_time.elapsed().as_secs_f64();
+    Throughput {
+        keys: state.generated() as f64 / elapsed,
+        seeds: state.seeds() as f64 / elapsed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workers_send_exactly_the_target_count() {
+        let patterns = PatternSet::new(&["1", "A"], MatchPosition::Anywhere, false);
+        let state = Arc::new(SearchState::new(vec![3, 2], 4));
+        let (tx, rx) = mpsc::channel();
+
+        for handle in spawn_threads(KeySource::Keypair, patterns, state.clone(), tx) {
+            handle.join().unwrap();
+        }
+        let found: Vec<FoundKey> = rx.iter().collect();
+
+        assert_eq!(found.len(), 5);
+        assert_eq!(found.iter().filter(|key| key.pattern == "1").count(), 3);
+        assert_eq!(found.iter().filter(|key| key.pattern == "A").count(), 2);
+        assert_eq!(state.found(), vec![3, 2]);
+    }
+
+    #[test]
+    fn workers_stop_when_nobody_takes_their_matches() {
+        let patterns = PatternSet::new(&["1"], MatchPosition::Anywhere, false);
+        let state = Arc::new(SearchState::new(vec![u64::MAX], 2));
+        let (tx, rx) = mpsc::channel();
+        drop(rx);
+
+        for handle in spawn_threads(KeySource::ScalarWalk, patterns, state.clone(), tx) {
+            handle.join().unwrap();
+        }
+        assert!(state.is_finished());
+    }
+
+    #[test]
+    fn mnemonic_matches_restore_from_their_phrase_and_path() {
+        let patterns = PatternSet::new(&["1"], MatchPosition::Anywhere, false);
+        let state = Arc::new(SearchState::new(vec![3], 2));
+        let (tx, rx) = mpsc::channel();
+        let source = KeySource::Mnemonic(PhraseSettings {
+            word_count: WordCount::Twelve,
+            passphrase: Password::new(String::new()),
+            accounts: 5..=9,
+        });
+
+        for handle in spawn_threads(source, patterns, state.clone(), tx) {
+            handle.join().unwrap();
+        }
+        let found: Vec<FoundKey> = rx.iter().collect();
+
+        assert_eq!(found.len(), 3);
+        assert_eq!(state.generated(), 5 * state.seeds());
+        for key in &found {
+            let seed_phrase = key.seed_phrase.as_ref().unwrap();
+            let phrase = mnemonic::parse(seed_phrase.phrase()).unwrap();
+            let keypair =
+                mnemonic::derive_keypair(&phrase, &Password::new(String::new()), &seed_phrase.path);
+            assert_eq!(keypair.pubkey().to_string(), key.public_key);
+            assert!((5..=9).any(|account| seed_phrase.path == DerivationPath::solana(account)));
+        }
+    }
+}
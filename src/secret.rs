@@ -0,0 +1,135 @@
+//! Handling of secret key material.
+//!
+//! Secrets only exist for confirmed matches, and wherever they go (the
+//! channel to the writer, the sinks' record buffers, decrypted keystores)
+//! they are held in containers that overwrite them with zeros when
+//! dropped. Candidates that don't match are wiped by their own types:
+//! `Keypair` zeroizes its secret key on drop, and so do
+//! [`ScalarWalk`](crate::walk::ScalarWalk) and
+//! [`ExpandedKeypair`](crate::expanded_key::ExpandedKeypair).
+
+use crate::expanded_key::ExpandedKeypair;
+use solana_sdk::{bs58, signature::Keypair};
+use std::{fmt, fmt::Write, io};
+use zeroize::{Zeroize, Zeroizing};
+
+pub const SECRET_KEY_BYTES: usize = 64;
+
+/// The 64 bytes of a Solana keypair or an expanded key, wiped when dropped.
+///
+/// The bytes live in a box so moving the key around never leaves copies
+/// behind, and `Debug` output leaves them out.
+#[derive(Clone)]
+pub struct SecretKey(Box<[u8; SECRET_KEY_BYTES]>);
+
+impl SecretKey {
+    pub fn from_keypair(keypair: &Keypair) -> Self {
+        let mut bytes = keypair.to_bytes();
+        let key = SecretKey::from_bytes(&bytes);
+        bytes.zeroize();
+        key
+    }
+
+    pub fn from_expanded(keypair: &ExpandedKeypair) -> Self {
+        let mut bytes = keypair.to_bytes();
+        let key = SecretKey::from_bytes(&bytes);
+        bytes.zeroize();
+        key
+    }
+
+    pub fn from_bytes(bytes: &[u8; SECRET_KEY_BYTES]) -> Self {
+        let mut key = SecretKey(Box::new([0; SECRET_KEY_BYTES]));
+        key.0.copy_from_slice(bytes);
+        key
+    }
+
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, String> {
+        let bytes: &[u8; SECRET_KEY_BYTES] = bytes.try_into().map_err(|_| {
+            format!(
+                "secret key must be {} bytes, got {}",
+                SECRET_KEY_BYTES,
+                bytes.len()
+            )
+        })?;
+        Ok(SecretKey::from_bytes(bytes))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; SECRET_KEY_BYTES] {
+        &self.0
+    }
+
+    pub fn to_base58(&self) -> Zeroizing<String> {
+        Zeroizing::new(bs58::encode(self.as_bytes()).into_string())
+    }
+
+    /// The JSON array of bytes `solana-keygen` writes to keypair files.
+    pub fn to_keypair_json(&self) -> Zeroizing<String> {
+        // Room for `[`, `]` and "255," per byte, so the string never
+        // reallocates and leaves an unwiped copy behind.
+        let mut json = Zeroizing::new(String::with_capacity(2 + 4 * SECRET_KEY_BYTES));
+        json.push('[');
+        for (i, byte) in self.as_bytes().iter().enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            write!(json, "{}", byte).expect("writing to a string can't fail");
+        }
+        json.push(']');
+        json
+    }
+}
+
+impl Drop for SecretKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl fmt::Debug for SecretKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SecretKey(..)")
+    }
+}
+
+/// Locks every page of the process, now and in the future, into memory so
+/// secrets are never written to swap.
+///
+/// Fails if the memory-lock limit (`ulimit -l`) is too low for the search;
+/// raise it or run with `CAP_IPC_LOCK`.
+#[cfg(unix)]
+pub fn lock_memory() -> io::Result<()> {
+    // SAFETY: mlockall only changes how the kernel pages this process.
+    if unsafe { libc::mlockall(libc::MCL_CURRENT | libc::MCL_FUTURE) } != 0 {
+        let e = io::Error::last_os_error();
+        return Err(io::Error::new(
+            e.kind(),
+            format!("cannot lock memory: {} (check `ulimit -l`)", e),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn lock_memory() -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "locking memory is only supported on Unix",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keypair_json_matches_solana_keygen_and_debug_hides_the_key() {
+        let keypair = Keypair::new();
+        let key = SecretKey::from_keypair(&keypair);
+        let mut sdk_json = Vec::new();
+        solana_sdk::signer::keypair::write_keypair(&keypair, &mut sdk_json).unwrap();
+
+        assert_eq!(key.to_keypair_json().as_bytes(), sdk_json);
+        assert_eq!(*key.to_base58(), keypair.to_base58_string());
+        assert_eq!(format!("{:?}", key), "SecretKey(..)");
+    }
+}
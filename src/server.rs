@@ -0,0 +1,813 @@
+//! A small HTTP/JSON API for running vanity searches on request.
+//!
+//! `vanaddy serve` keeps a list of jobs and one pool of worker threads.
+//! Each job is an ordinary search whose matches are sealed to the
+//! requester's [`seal`] recipients as they are found, so the server never
+//! writes or hands out a secret key in the clear. Jobs either run one at a
+//! time in the order they came in ([`Schedule::Queue`]) or take turns of
+//! [`SLICE`] on the whole pool ([`Schedule::Fair`]), each turn being a
+//! [`spawn_threads`](search::spawn_threads) run for the matches the job
+//! still wants.
+//!
+//! ```text
+//! POST   /jobs               submit a job, answered with its status
+//! GET    /jobs               the status of every job
+//! GET    /jobs/ID            one job's status, with live key counts and ETA
+//! DELETE /jobs/ID            cancel a job that hasn't finished
+//! GET    /jobs/ID/results    the sealed results so far, for `vanaddy unseal`
+//! ```
+//!
+//! A job is submitted as:
+//!
+//! ```json
+//! {"patterns":["Sol","Pay:2"],"count":1,"position":"prefix","case_sensitive":true,
+//!  "regex":false,"generator":"keypair","recipients":["vanaddy-x25519:..."]}
+//! ```
+//!
+//! Everything but `patterns` and `recipients` is optional, with the
+//! defaults above; patterns take a count like on the command line. Errors
+//! are answered as `{"error":"..."}`. There is no authentication, so listen
+//! only where the people allowed to submit jobs can reach.
+//!
+//! Jobs are kept in memory. Submissions are refused with 503 while
+//! [`MAX_UNFINISHED`] jobs are queued or running, and only the newest
+//! [`MAX_FINISHED`] finished jobs are kept, with their results, so fetch
+//! results before they are dropped. A job asks for at most
+//! [`MAX_MATCHES`] matches.
+
+use crate::{
+    cli,
+    estimate::{self, Difficulty, SearchDifficulty},
+    matcher::{MatchPosition, PatternSet},
+    output,
+    seal::Recipient,
+    search::{self, FoundKey, Generator, SearchConfig},
+    state::SearchState,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc, Arc, Condvar, Mutex, MutexGuard,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How long a job runs before the next one gets the pool, when jobs are
+/// shared fairly.
+pub const SLICE: Duration = Duration::from_secs(1);
+
+/// How often a running turn checks whether it should end.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a client has to send its request.
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longer request lines, headers and bodies are refused.
+const MAX_HEADER_LEN: u64 = 8 * 1024;
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+/// Submissions are refused while this many jobs are queued or running.
+pub const MAX_UNFINISHED: usize = 64;
+
+/// Finished jobs kept for their status and results; older ones are
+/// dropped when new jobs come in.
+pub const MAX_FINISHED: usize = 256;
+
+/// Matches one job may ask for, over all its patterns. Their sealed
+/// records are kept in memory until the job is dropped.
+pub const MAX_MATCHES: u64 = 1000;
+
+/// How jobs share the worker threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Schedule {
+    /// One job at a time, in the order they were submitted
+    Queue,
+    /// Every unfinished job in turn, for a short slice each
+    Fair,
+}
+
+/// A job as submitted.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct JobRequest {
+    patterns: Vec<String>,
+    #[serde(default = "default_count")]
+    count: u64,
+    #[serde(default = "default_position")]
+    position: String,
+    #[serde(default = "default_case_sensitive")]
+    case_sensitive: bool,
+    #[serde(default)]
+    regex: bool,
+    #[serde(default = "default_generator")]
+    generator: String,
+    recipients: Vec<String>,
+}
+
+fn default_count() -> u64 {
+    1
+}
+
+fn default_position() -> String {
+    "prefix".to_string()
+}
+
+fn default_case_sensitive() -> bool {
+    true
+}
+
+fn default_generator() -> String {
+    "keypair".to_string()
+}
+
+impl JobRequest {
+    /// The search the request asks for, and who to seal its results to.
+    fn into_search(self) -> Result<(SearchConfig, Vec<Recipient>), String> {
+        if self.patterns.is_empty() {
+            return Err("no vanity strings given".to_string());
+        }
+        if self.count == 0 {
+            return Err("count must be at least 1".to_string());
+        }
+        let generator = Generator::from_str(&self.generator, false)?;
+        if !matches!(
+            generator,
+            Generator::Keypair | Generator::ScalarWalk | Generator::Mnemonic
+        ) {
+            return Err(format!(
+                "the server runs the keypair, scalar-walk and mnemonic generators, not `{}`",
+                self.generator
+            ));
+        }
+        if self.recipients.is_empty() {
+            return Err("results are sealed, so a job needs at least one recipient".to_string());
+        }
+        let recipients = self
+            .recipients
+            .iter()
+            .map(|recipient| recipient.parse())
+            .collect::<Result<_, String>>()?;
+        let targets = self
+            .patterns
+            .iter()
+            .map(|spec| cli::parse_target(spec, self.count))
+            .collect::<Result<Vec<_>, _>>()?;
+        let total = targets
+            .iter()
+            .fold(0u64, |sum, target| sum.saturating_add(target.count));
+        if total > MAX_MATCHES {
+            return Err(format!(
+                "a job can ask for at most {} matches, not {}",
+                MAX_MATCHES, total
+            ));
+        }
+        let config = SearchConfig {
+            case_sensitive: self.case_sensitive,
+            position: MatchPosition::from_str(&self.position, false)?,
+            regex: self.regex,
+            ..SearchConfig::new(targets, generator)
+        };
+        config.validate_patterns()?;
+        Ok((config, recipients))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobState {
+    Queued,
+    Running,
+    Done,
+    Cancelled,
+}
+
+impl JobState {
+    fn is_over(self) -> bool {
+        matches!(self, JobState::Done | JobState::Cancelled)
+    }
+}
+
+struct JobEntry {
+    id: u64,
+    config: SearchConfig,
+    patterns: PatternSet,
+    difficulty: Option<SearchDifficulty>,
+    mode: String,
+    recipients: Vec<Recipient>,
+    state: JobState,
+    /// Matches found for each pattern in finished turns.
+    found: Vec<u64>,
+    /// Keys generated in finished turns.
+    generated: u64,
+    /// Time spent running in finished turns.
+    run_time: Duration,
+    /// The turn in progress and when it started.
+    turn: Option<(Arc<SearchState>, Instant)>,
+    /// Sealed results, one JSON line each.
+    results: Vec<String>,
+}
+
+impl JobEntry {
+    fn status(&self) -> JobStatus {
+        let (mut found, mut generated, mut run_time) =
+            (self.found.clone(), self.generated, self.run_time);
+        if let Some((state, started)) = &self.turn {
+            for (total, turn) in found.iter_mut().zip(state.found()) {
+                *total += turn;
+            }
+            generated += state.generated();
+            run_time += started.elapsed();
+        }
+        let remaining: Vec<u64> = self
+            .config
+            .targets
+            .iter()
+            .zip(&found)
+            .map(|(target, &found)| target.count - found)
+            .collect();
+        let rate = if run_time.is_zero() {
+            0.0
+        } else {
+            generated as f64 / run_time.as_secs_f64()
+        };
+        let eta = (!self.state.is_over())
+            .then(|| self.difficulty.as_ref()?.eta(&remaining, rate))
+            .flatten();
+        JobStatus {
+            id: self.id,
+            status: self.state,
+            mode: self.mode.clone(),
+            patterns: self
+                .config
+                .targets
+                .iter()
+                .zip(found)
+                .map(|(target, found)| PatternStatus {
+                    pattern: target.pattern.clone(),
+                    count: target.count,
+                    found,
+                })
+                .collect(),
+            attempts: generated,
+            rate,
+            run_secs: run_time.as_secs_f64(),
+            eta_secs: eta.map(|eta| eta.as_secs_f64()),
+            eta: eta.map(estimate::format_duration),
+            results: self.results.len(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct JobStatus {
+    id: u64,
+    status: JobState,
+    mode: String,
+    patterns: Vec<PatternStatus>,
+    /// Keys generated so far.
+    attempts: u64,
+    /// Keys per second while the job had the pool.
+    rate: f64,
+    run_secs: f64,
+    /// Expected time to finish if the job keeps the pool to itself.
+    eta_secs: Option<f64>,
+    eta: Option<String>,
+    results: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct PatternStatus {
+    pattern: String,
+    count: u64,
+    found: u64,
+}
+
+/// The jobs, shared by the connections and the scheduler, which waits on
+/// the condvar for work.
+#[derive(Default)]
+struct Jobs {
+    list: Mutex<Vec<JobEntry>>,
+    submitted: Condvar,
+    /// The last id handed out; ids aren't reused when jobs are dropped.
+    last_id: AtomicU64,
+}
+
+impl Jobs {
+    fn lock(&self) -> MutexGuard<'_, Vec<JobEntry>> {
+        self.list.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Serves the API on `listener`, running jobs on `threads` threads. Only
+/// returns if the listener fails.
+pub fn serve(listener: TcpListener, threads: usize, schedule: Schedule) -> io::Result<()> {
+    let jobs = Arc::new(Jobs::default());
+    {
+        let jobs = jobs.clone();
+        thread::spawn(move || schedule_jobs(&jobs, threads, schedule));
+    }
+    loop {
+        let (stream, peer) = listener.accept()?;
+        let jobs = jobs.clone();
+        thread::spawn(move || {
+            if let Err(e) = handle_connection(stream, &jobs) {
+                eprintln!("\nerror: request from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+/// Runs turns of unfinished jobs forever.
+fn schedule_jobs(jobs: &Jobs, threads: usize, schedule: Schedule) {
+    let mut last = 0;
+    loop {
+        let (id, state, source, patterns) = {
+            let mut list = jobs.lock();
+            let index = loop {
+                let unfinished = |entry: &&JobEntry| !entry.state.is_over();
+                let next = match schedule {
+                    Schedule::Queue => list.iter().find(unfinished),
+                    Schedule::Fair => list
+                        .iter()
+                        .filter(unfinished)
+                        .find(|entry| entry.id > last)
+                        .or_else(|| list.iter().find(unfinished)),
+                };
+                match next {
+                    Some(entry) => break list.iter().position(|e| e.id == entry.id).unwrap(),
+                    None => list = jobs.submitted.wait(list).unwrap_or_else(|e| e.into_inner()),
+                }
+            };
+            let entry = &mut list[index];
+            let remaining = entry
+                .config
+                .targets
+                .iter()
+                .zip(&entry.found)
+                .map(|(target, &found)| target.count - found)
+                .collect();
+            let state = Arc::new(SearchState::new(remaining, threads));
+            entry.state = JobState::Running;
+            entry.turn = Some((state.clone(), Instant::now()));
+            (
+                entry.id,
+                state,
+                entry.config.key_source(None),
+                entry.patterns.clone(),
+            )
+        };
+        last = id;
+
+        let (tx, rx) = mpsc::channel();
+        let handles = search::spawn_threads(source, patterns, state.clone(), tx);
+        let deadline = Instant::now() + SLICE;
+        loop {
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(found) => store_result(jobs, id, &found),
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    // A turn only ends early for another job's sake.
+                    if schedule == Schedule::Fair
+                        && Instant::now() >= deadline
+                        && jobs
+                            .lock()
+                            .iter()
+                            .any(|entry| entry.id != id && !entry.state.is_over())
+                    {
+                        state.stop();
+                    }
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        for handle in handles {
+            if handle.join().is_err() {
+                eprintln!("\nerror: a worker thread of job {} panicked", id);
+                state.stop();
+            }
+        }
+
+        let mut list = jobs.lock();
+        let entry = list
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .expect("jobs are not dropped during a turn");
+        if let Some((_, started)) = entry.turn.take() {
+            entry.run_time += started.elapsed();
+        }
+        entry.generated += state.generated();
+        for (total, turn) in entry.found.iter_mut().zip(state.found()) {
+            *total += turn;
+        }
+        if entry.state != JobState::Cancelled {
+            entry.state = if state.all_found() {
+                JobState::Done
+            } else {
+                JobState::Queued
+            };
+        }
+    }
+}
+
+fn store_result(jobs: &Jobs, id: u64, found: &FoundKey) {
+    let mut list = jobs.lock();
+    let entry = list.iter_mut().find(|entry| entry.id == id);
+    if let Some(entry) = entry.filter(|entry| entry.results.len() < MAX_MATCHES as usize) {
+        let record = output::sealed_record(found, &entry.mode, &entry.recipients);
+        entry.results.push(record);
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn json(status: u16, value: &impl Serialize) -> Self {
+        Response {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_string(value).expect("statuses serialize"),
+        }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Response::json(status, &serde_json::json!({ "error": message.into() }))
+    }
+}
+
+fn handle_connection(stream: TcpStream, jobs: &Jobs) -> io::Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let response = match read_request(&mut reader) {
+        Ok(request) => route(&request, jobs),
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Response::error(400, e.to_string()),
+        Err(e) => return Err(e),
+    };
+    write_response(stream, &response)
+}
+
+/// Reads a request line, headers and a `Content-Length` body.
+fn read_request(reader: &mut impl BufRead) -> io::Result<Request> {
+    let mut head = reader.take(MAX_HEADER_LEN);
+    let mut line = String::new();
+    head.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (method, path) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(path), Some(version)) if version.starts_with("HTTP/1.") => {
+            (method.to_owned(), path.to_owned())
+        }
+        _ => return Err(invalid("malformed request line")),
+    };
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if head.read_line(&mut line)? == 0 {
+            return Err(invalid("headers too long or cut short"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| invalid("invalid Content-Length"))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_LEN {
+        return Err(invalid("request body too long"));
+    }
+    let mut body = vec![0; content_length];
+    head.into_inner().read_exact(&mut body)?;
+    Ok(Request { method, path, body })
+}
+
+fn write_response(mut stream: TcpStream, response: &Response) -> io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        503 => "Service Unavailable",
+        _ => "Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )?;
+    stream.flush()
+}
+
+fn route(request: &Request, jobs: &Jobs) -> Response {
+    let segments: Vec<&str> = request.path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["jobs"]) => submit(&request.body, jobs),
+        ("GET", ["jobs"]) => {
+            let statuses: Vec<JobStatus> = jobs.lock().iter().map(JobEntry::status).collect();
+            Response::json(200, &statuses)
+        }
+        (method, ["jobs", id, rest @ ..]) if rest.len() <= 1 => {
+            let Ok(id) = id.parse::<u64>() else {
+                return Response::error(404, format!("no job `{}`", id));
+            };
+            let mut list = jobs.lock();
+            let Some(entry) = list.iter_mut().find(|entry| entry.id == id) else {
+                return Response::error(404, format!("no job {}", id));
+            };
+            match (method, rest) {
+                ("GET", []) => Response::json(200, &entry.status()),
+                ("DELETE", []) => cancel(entry),
+                ("GET", ["results"]) => Response {
+                    status: 200,
+                    content_type: "application/x-ndjson",
+                    body: entry
+                        .results
+                        .iter()
+                        .map(|record| format!("{}\n", record))
+                        .collect(),
+                },
+                (_, []) | (_, ["results"]) => Response::error(405, "method not allowed"),
+                _ => Response::error(404, "not found"),
+            }
+        }
+        (_, ["jobs"]) => Response::error(405, "method not allowed"),
+        _ => Response::error(404, "not found"),
+    }
+}
+
+fn submit(body: &[u8], jobs: &Jobs) -> Response {
+    let request: JobRequest = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(e) => return Response::error(400, format!("invalid job: {}", e)),
+    };
+    let (config, recipients) = match request.into_search() {
+        Ok(search) => search,
+        Err(e) => return Response::error(400, e),
+    };
+    let patterns = config.patterns();
+    let difficulty = SearchDifficulty::new(&Difficulty::for_patterns(&patterns), &config.counts());
+    let mut list = jobs.lock();
+    if list.iter().filter(|entry| !entry.state.is_over()).count() >= MAX_UNFINISHED {
+        return Response::error(
+            503,
+            format!(
+                "{} jobs are already waiting; try again later",
+                MAX_UNFINISHED
+            ),
+        );
+    }
+    drop_finished(&mut list, MAX_FINISHED - 1);
+    let entry = JobEntry {
+        id: jobs.last_id.fetch_add(1, Ordering::Relaxed) + 1,
+        found: vec![0; config.targets.len()],
+        mode: config.mode(),
+        config,
+        patterns,
+        difficulty,
+        recipients,
+        state: JobState::Queued,
+        generated: 0,
+        run_time: Duration::ZERO,
+        turn: None,
+        results: Vec::new(),
+    };
+    let status = entry.status();
+    list.push(entry);
+    jobs.submitted.notify_one();
+    Response::json(201, &status)
+}
+
+/// Drops the oldest finished jobs until at most `keep` are left. Jobs
+/// cancelled in the middle of a turn stay until the turn ends.
+fn drop_finished(list: &mut Vec<JobEntry>, keep: usize) {
+    let finished = |entry: &JobEntry| entry.state.is_over() && entry.turn.is_none();
+    let mut excess = list
+        .iter()
+        .filter(|entry| finished(entry))
+        .count()
+        .saturating_sub(keep);
+    list.retain(|entry| {
+        let drop = excess > 0 && finished(entry);
+        excess -= drop as usize;
+        !drop
+    });
+}
+
+/// Stops a job, keeping the results it already has.
+fn cancel(entry: &mut JobEntry) -> Response {
+    if entry.state.is_over() {
+        return Response::error(409, format!("job {} has already finished", entry.id));
+    }
+    entry.state = JobState::Cancelled;
+    if let Some((state, _)) = &entry.turn {
+        state.stop();
+    }
+    Response::json(200, &entry.status())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seal::{self, Identity};
+    use serde_json::Value;
+    use std::net::SocketAddr;
+
+    fn start(schedule: Schedule) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || serve(listener, 1, schedule));
+        addr
+    }
+
+    fn request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, body.to_owned())
+    }
+
+    fn submit(addr: SocketAddr, job: Value) -> u64 {
+        let (status, body) = request(addr, "POST", "/jobs", &job.to_string());
+        assert_eq!(status, 201, "{}", body);
+        serde_json::from_str::<Value>(&body).unwrap()["id"]
+            .as_u64()
+            .unwrap()
+    }
+
+    fn wait_for(addr: SocketAddr, id: u64, wanted: &str) -> Value {
+        let started = Instant::now();
+        loop {
+            let (_, body) = request(addr, "GET", &format!("/jobs/{}", id), "");
+            let status: Value = serde_json::from_str(&body).unwrap();
+            if status["status"] == wanted {
+                return status;
+            }
+            assert!(started.elapsed() < Duration::from_secs(60), "{}", status);
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn jobs_are_run_and_their_results_sealed() {
+        let addr = start(Schedule::Queue);
+        let identity = Identity::generate();
+        let id = submit(
+            addr,
+            serde_json::json!({
+                "patterns": ["a"],
+                "count": 2,
+                "position": "anywhere",
+                "case_sensitive": false,
+                "recipients": [identity.recipient().to_string()],
+            }),
+        );
+        let status = wait_for(addr, id, "done");
+        assert_eq!(status["patterns"][0]["found"], 2);
+        assert!(status["attempts"].as_u64().unwrap() >= 2);
+        assert_eq!(status["eta"], Value::Null);
+
+        let (code, results) = request(addr, "GET", &format!("/jobs/{}/results", id), "");
+        assert_eq!(code, 200);
+        assert_eq!(results.lines().count(), 2);
+        for record in results.lines() {
+            let (address, plaintext) = seal::open(record, &identity).unwrap();
+            assert!(address.to_lowercase().contains('a'));
+            let record: Value = serde_json::from_slice(&plaintext).unwrap();
+            assert_eq!(record["pubkey"], address);
+            assert!(record["secret"].is_string());
+        }
+        assert_eq!(request(addr, "DELETE", &format!("/jobs/{}", id), "").0, 409);
+    }
+
+    #[test]
+    fn bad_jobs_are_refused_and_running_ones_cancelled() {
+        let addr = start(Schedule::Queue);
+        let recipient = Identity::generate().recipient().to_string();
+        for job in [
+            serde_json::json!({ "patterns": ["abc"], "recipients": [] }),
+            serde_json::json!({ "patterns": ["0OIl"], "recipients": [recipient] }),
+            serde_json::json!({ "patterns": ["abc"], "generator": "pda", "recipients": [recipient] }),
+            serde_json::json!({ "patterns": ["1"], "count": 1_000_000_000_000u64, "recipients": [recipient] }),
+            serde_json::json!({ "patterns": ["1:600", "A:600"], "recipients": [recipient] }),
+            serde_json::json!({ "patterns": ["1:18446744073709551615", "A"], "recipients": [recipient] }),
+        ] {
+            assert_eq!(request(addr, "POST", "/jobs", &job.to_string()).0, 400);
+        }
+        assert_eq!(request(addr, "GET", "/jobs/7", "").0, 404);
+
+        let id = submit(
+            addr,
+            serde_json::json!({ "patterns": ["zzzzzzzzz"], "recipients": [recipient] }),
+        );
+        wait_for(addr, id, "running");
+        let (code, body) = request(addr, "DELETE", &format!("/jobs/{}", id), "");
+        assert_eq!(code, 200, "{}", body);
+        assert_eq!(request(addr, "DELETE", &format!("/jobs/{}", id), "").0, 409);
+        let (_, jobs) = request(addr, "GET", "/jobs", "");
+        assert_eq!(
+            serde_json::from_str::<Value>(&jobs).unwrap()[0]["status"],
+            "cancelled"
+        );
+    }
+
+    #[test]
+    fn submissions_wait_for_room_in_the_queue() {
+        let addr = start(Schedule::Queue);
+        let job = serde_json::json!({
+            "patterns": ["zzzzzzzzz"],
+            "recipients": [Identity::generate().recipient().to_string()],
+        });
+        let first = submit(addr, job.clone());
+        for _ in 1..MAX_UNFINISHED {
+            submit(addr, job.clone());
+        }
+        assert_eq!(request(addr, "POST", "/jobs", &job.to_string()).0, 503);
+        assert_eq!(
+            request(addr, "DELETE", &format!("/jobs/{}", first), "").0,
+            200
+        );
+        submit(addr, job);
+    }
+
+    #[test]
+    fn only_the_newest_finished_jobs_are_kept() {
+        let jobs = Jobs::default();
+        let job = serde_json::json!({
+            "patterns": ["zzzzzzzzz"],
+            "recipients": [Identity::generate().recipient().to_string()],
+        });
+        for _ in 0..5 {
+            assert_eq!(super::submit(job.to_string().as_bytes(), &jobs).status, 201);
+        }
+        let ids = |list: &[JobEntry]| list.iter().map(|entry| entry.id).collect::<Vec<_>>();
+        let mut list = jobs.lock();
+        list[0].state = JobState::Done;
+        list[1].state = JobState::Cancelled;
+        list[1].turn = Some((Arc::new(SearchState::new(vec![1], 1)), Instant::now()));
+        list[2].state = JobState::Cancelled;
+        list[3].state = JobState::Done;
+        drop_finished(&mut list, 1);
+        assert_eq!(ids(&list), [2, 4, 5]);
+        drop(list);
+
+        super::submit(job.to_string().as_bytes(), &jobs);
+        assert_eq!(ids(&jobs.lock()), [2, 4, 5, 6]);
+    }
+
+    #[test]
+    fn fair_scheduling_finishes_small_jobs_behind_big_ones() {
+        let addr = start(Schedule::Fair);
+        let recipient = Identity::generate().recipient().to_string();
+        let big = submit(
+            addr,
+            serde_json::json!({ "patterns": ["zzzzzzzzz"], "recipients": [recipient] }),
+        );
+        let small = submit(
+            addr,
+            serde_json::json!({ "patterns": ["a"], "position": "anywhere", "recipients": [recipient] }),
+        );
+        wait_for(addr, small, "done");
+        let (_, body) = request(addr, "GET", &format!("/jobs/{}", big), "");
+        let big_status: Value = serde_json::from_str(&body).unwrap();
+        assert!(big_status["attempts"].as_u64().unwrap() > 0);
+        assert!(big_status["eta"].is_string(), "{}", big_status);
+    }
+}
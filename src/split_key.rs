@@ -0,0 +1,264 @@
+//! Outsourcing a search without handing over the key.
+//!
+//! The requester keeps a secret scalar `a` and publishes only its point,
+//! the partial key `A = a * B`. A searcher walks offsets `b` until
+//! `A + b * B` is a vanity address, and sends back `b`. Only the requester
+//! can then sign for the address, with the combined scalar `a + b`; the
+//! searcher knows `b` and `A`, and getting `a` out of `A` is the discrete
+//! logarithm problem ed25519 rests on.
+//!
+//! Both halves are written as prefixed base58 strings, like
+//! [`seal`](crate::seal) keys, and the offset as hex:
+//!
+//! ```text
+//! VANADDY-SPLIT-SECRET:<base58 scalar>
+//! vanaddy-partial:<base58 point>
+//! ```
+
+use crate::{
+    expanded_key::ExpandedKeypair,
+    hex::{hex, parse_hex},
+};
+use curve25519_dalek::{edwards::CompressedEdwardsY, EdwardsPoint, Scalar};
+use rand::{rngs::OsRng, RngCore};
+use solana_sdk::{bs58, pubkey::Pubkey};
+use std::{fmt, str::FromStr};
+use zeroize::{Zeroize, Zeroizing};
+
+const SECRET_PREFIX: &str = "VANADDY-SPLIT-SECRET:";
+const PARTIAL_PREFIX: &str = "vanaddy-partial:";
+
+/// The requester's half of a split key. Wiped when dropped.
+#[derive(Clone)]
+pub struct SplitSecret {
+    scalar: Scalar,
+}
+
+impl SplitSecret {
+    pub fn generate() -> Self {
+        let mut seed = Zeroizing::new([0u8; 64]);
+        OsRng.fill_bytes(&mut *seed);
+        SplitSecret {
+            scalar: Scalar::from_bytes_mod_order_wide(&seed),
+        }
+    }
+
+    /// The partial key to hand to searchers.
+    pub fn partial_key(&self) -> PartialKey {
+        PartialKey::from_point(EdwardsPoint::mul_base(&self.scalar))
+    }
+
+    /// Reads a secret file: the first line starting with the secret prefix,
+    /// ignoring `#` comments and blank lines.
+    pub fn from_file_contents(contents: &str) -> Result<Self, String> {
+        contents
+            .lines()
+            .map(str::trim)
+            .find(|line| line.starts_with(SECRET_PREFIX))
+            .ok_or_else(|| "no split-key secret found".to_string())?
+            .parse()
+    }
+
+    /// The key for the address a searcher found at `offset`.
+    pub fn combine(&self, offset: &Offset) -> ExpandedKeypair {
+        ExpandedKeypair::from_scalar(self.scalar + offset.0)
+    }
+}
+
+impl Drop for SplitSecret {
+    fn drop(&mut self) {
+        self.scalar.zeroize();
+    }
+}
+
+impl fmt::Display for SplitSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let encoded = Zeroizing::new(bs58::encode(self.scalar.as_bytes()).into_string());
+        write!(f, "{}{}", SECRET_PREFIX, encoded.as_str())
+    }
+}
+
+impl fmt::Debug for SplitSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SplitSecret")
+            .field("partial_key", &self.partial_key())
+            .finish_non_exhaustive()
+    }
+}
+
+impl FromStr for SplitSecret {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let key = s
+            .strip_prefix(SECRET_PREFIX)
+            .ok_or_else(|| format!("split-key secret must start with `{}`", SECRET_PREFIX))?;
+        let bytes = Zeroizing::new(decode_key(key)?);
+        let scalar = Option::from(Scalar::from_canonical_bytes(*bytes))
+            .ok_or("split-key secret is not a reduced scalar")?;
+        Ok(SplitSecret { scalar })
+    }
+}
+
+/// The public point searchers add offsets to.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PartialKey {
+    point: EdwardsPoint,
+}
+
+impl PartialKey {
+    fn from_point(point: EdwardsPoint) -> Self {
+        PartialKey { point }
+    }
+
+    pub fn point(&self) -> &EdwardsPoint {
+        &self.point
+    }
+
+    /// The address at `offset`, `A + offset * B`.
+    pub fn address(&self, offset: &Offset) -> Pubkey {
+        Pubkey::new_from_array(
+            (self.point + EdwardsPoint::mul_base(&offset.0))
+                .compress()
+                .to_bytes(),
+        )
+    }
+}
+
+impl fmt::Display for PartialKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            PARTIAL_PREFIX,
+            bs58::encode(self.point.compress().as_bytes()).into_string()
+        )
+    }
+}
+
+impl fmt::Debug for PartialKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "PartialKey({})", self)
+    }
+}
+
+impl FromStr for PartialKey {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let key = s
+            .strip_prefix(PARTIAL_PREFIX)
+            .ok_or_else(|| format!("partial key must start with `{}`", PARTIAL_PREFIX))?;
+        let point = CompressedEdwardsY(decode_key(key)?)
+            .decompress()
+            .ok_or("partial key is not a point on the curve")?;
+        // A point outside the prime-order subgroup can't come from
+        // `SplitSecret`, and would make the found addresses keys whose
+        // signatures some verifiers reject.
+        if point.is_small_order() || !point.is_torsion_free() {
+            return Err("partial key is not a usable public key".to_string());
+        }
+        Ok(PartialKey::from_point(point))
+    }
+}
+
+/// What a searcher found: the scalar to add to the requester's secret.
+/// On its own it signs for nothing.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Offset(Scalar);
+
+impl Offset {
+    pub fn new(scalar: Scalar) -> Self {
+        Offset(scalar)
+    }
+}
+
+impl fmt::Display for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&hex(self.0.as_bytes()))
+    }
+}
+
+impl fmt::Debug for Offset {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Offset({})", self)
+    }
+}
+
+impl FromStr for Offset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let bytes: [u8; 32] = parse_hex(s)
+            .map_err(|e| format!("invalid offset: {}", e))?
+            .try_into()
+            .map_err(|bytes: Vec<u8>| format!("offset must be 32 bytes, got {}", bytes.len()))?;
+        Option::from(Scalar::from_canonical_bytes(bytes))
+            .map(Offset)
+            .ok_or_else(|| "offset is not a reduced scalar".to_string())
+    }
+}
+
+/// How the requester turns a match into its key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SplitMatch {
+    pub partial_key: PartialKey,
+    pub offset: Offset,
+}
+
+fn decode_key(key: &str) -> Result<[u8; 32], String> {
+    let bytes = bs58::decode(key)
+        .into_vec()
+        .map_err(|e| format!("invalid base58 key: {}", e))?;
+    bytes
+        .try_into()
+        .map_err(|bytes: Vec<u8>| format!("key must be 32 bytes, got {}", bytes.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::walk::ScalarWalk;
+    use solana_sdk::signature::Signer;
+
+    #[test]
+    fn only_the_combined_key_signs_for_the_address() {
+        let secret = SplitSecret::generate();
+        let partial: PartialKey = secret.partial_key().to_string().parse().unwrap();
+
+        // The searcher's side: a walk from the partial key.
+        let mut walk = ScalarWalk::from_partial(partial.point(), 4);
+        walk.next_batch();
+        let address = Pubkey::new_from_array(walk.keys()[2]);
+        let offset: Offset = Offset::new(walk.scalar(2)).to_string().parse().unwrap();
+        assert_eq!(partial.address(&offset), address);
+
+        let key = secret.combine(&offset);
+        assert_eq!(key.pubkey(), address);
+        assert!(key.sign(b"vanity").verify(address.as_ref(), b"vanity"));
+
+        // Everything the searcher has, used as a key on its own.
+        let alone = ExpandedKeypair::from_scalar(walk.scalar(2));
+        assert_ne!(alone.pubkey(), address);
+        assert!(!alone.sign(b"vanity").verify(address.as_ref(), b"vanity"));
+        let restored: SplitSecret = secret.to_string().parse().unwrap();
+        assert_eq!(restored.partial_key(), partial);
+    }
+
+    #[test]
+    fn keys_are_checked() {
+        let partial = SplitSecret::generate().partial_key().to_string();
+        assert!(partial
+            .strip_prefix(PARTIAL_PREFIX)
+            .unwrap()
+            .parse::<PartialKey>()
+            .is_err());
+        let mut identity = [0u8; 32];
+        identity[0] = 1;
+        let identity = format!("{}{}", PARTIAL_PREFIX, bs58::encode(identity).into_string());
+        assert!(identity.parse::<PartialKey>().is_err());
+        assert!("00".parse::<Offset>().is_err());
+        assert!("ff".repeat(32).parse::<Offset>().is_err());
+        assert!(SplitSecret::from_file_contents("# nothing here\n").is_err());
+    }
+}
@@ -0,0 +1,200 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+/// Progress of one search, shared by the workers, the progress line and the
+/// final report without any locks.
+///
+/// Each worker bumps its own generated-keys counter, padded to a cache line
+/// so the workers don't invalidate each other's; readers add them up.
+/// Results are claimed per pattern with a compare-and-swap that never
+/// lets a count go past its target, so a search finds exactly the number of
+/// wallets asked for however many workers hit at once.
+#[derive(Debug)]
+pub struct SearchState {
+    targets: Vec<u64>,
+    found: Vec<AtomicU64>,
+    /// Results still to be claimed across all patterns.
+    remaining: AtomicU64,
+    generated: Vec<PaddedCounter>,
+    /// Keys generated by worker processes of a distributed search.
+    remote: AtomicU64,
+    /// Seed phrases stretched by mnemonic workers; each yields several keys.
+    seeds: AtomicU64,
+    stop: AtomicBool,
+    interrupted: AtomicBool,
+}
+
+#[derive(Debug, Default)]
+#[repr(align(128))]
+struct PaddedCounter(AtomicU64);
+
+impl SearchState {
+    /// State for a search wanting `targets[i]` matches of pattern `i`,
+    /// spread over `workers` threads.
+    pub fn new(targets: Vec<u64>, workers: usize) -> Self {
+        let remaining = targets.iter().fold(0u64, |sum, &n| sum.saturating_add(n));
+        SearchState {
+            found: targets.iter().map(|_| AtomicU64::new(0)).collect(),
+            targets,
+            remaining: AtomicU64::new(remaining),
+            generated: (0..workers).map(|_| PaddedCounter::default()).collect(),
+            remote: AtomicU64::new(0),
+            seeds: AtomicU64::new(0),
+            stop: AtomicBool::new(false),
+            interrupted: AtomicBool::new(false),
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.generated.len()
+    }
+
+    /// Records `count` more keys generated by `worker`.
+    pub fn add_generated(&self, worker: usize, count: u64) {
+        self.generated[worker].0.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Records `count` more keys generated by a worker process.
+    pub fn add_remote(&self, count: u64) {
+        self.remote.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Keys generated so far by all workers, local and remote.
+    pub fn generated(&self) -> u64 {
+        self.generated
+            .iter()
+            .map(|counter| counter.0.load(Ordering::Relaxed))
+            .sum::<u64>()
+            + self.remote.load(Ordering::Relaxed)
+    }
+
+    /// Records `count` more seed phrases stretched. Only called once per
+    /// batch of phrases, which take far longer than key batches, so one
+    /// shared counter is enough.
+    pub fn add_seeds(&self, count: u64) {
+        self.seeds.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Seed phrases stretched so far by all workers.
+    pub fn seeds(&self) -> u64 {
+        self.seeds.load(Ordering::Relaxed)
+    }
+
+    /// Claims a result for the first pattern in `hits` that still needs
+    /// one, returning its index, or `None` if they are all done.
+    pub fn claim(&self, hits: &[usize]) -> Option<usize> {
+        let index = hits.iter().copied().find(|&i| {
+            self.found[i]
+                .fetch_update(Ordering::AcqRel, Ordering::Acquire, |found| {
+                    (found < self.targets[i]).then_some(found + 1)
+                })
+                .is_ok()
+        })?;
+        self.remaining.fetch_sub(1, Ordering::AcqRel);
+        Some(index)
+    }
+
+    /// Matches found so far for each pattern.
+    pub fn found(&self) -> Vec<u64> {
+        self.found
+            .iter()
+            .map(|found| found.load(Ordering::Acquire))
+            .collect()
+    }
+
+    /// Matches still wanted for each pattern.
+    pub fn remaining(&self) -> Vec<u64> {
+        self.targets
+            .iter()
+            .zip(self.found())
+            .map(|(target, found)| target.saturating_sub(found))
+            .collect()
+    }
+
+    /// Gives up on pattern `index` as if its target had been reached, e.g.
+    /// because another process of a distributed search reached it.
+    pub fn retire(&self, index: usize) {
+        let previous = self.found[index].swap(self.targets[index], Ordering::AcqRel);
+        self.remaining
+            .fetch_sub(self.targets[index] - previous, Ordering::AcqRel);
+    }
+
+    /// Whether every pattern has reached its target.
+    pub fn all_found(&self) -> bool {
+        self.remaining.load(Ordering::Acquire) == 0
+    }
+
+    /// Asks the workers to finish their current batch and exit.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops the search because the user asked it to, e.g. with Ctrl-C.
+    pub fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::Relaxed);
+        self.stop();
+    }
+
+    pub fn was_interrupted(&self) -> bool {
+        self.interrupted.load(Ordering::Relaxed)
+    }
+
+    /// Whether the workers should exit, either because everything was found
+    /// or because the search was stopped.
+    pub fn is_finished(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.all_found()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn claims_never_exceed_targets() {
+        let state = Arc::new(SearchState::new(vec![5, 3, 0], 8));
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let state = Arc::clone(&state);
+                thread::spawn(move || {
+                    let mut claimed = 0;
+                    for _ in 0..10_000 {
+                        if state.claim(&[2, 0, 1]).is_some() {
+                            claimed += 1;
+                        }
+                    }
+                    claimed
+                })
+            })
+            .collect();
+        let claimed: u64 = handles.into_iter().map(|h| h.join().unwrap()).sum();
+
+        assert_eq!(claimed, 8);
+        assert_eq!(state.found(), vec![5, 3, 0]);
+        assert!(state.all_found());
+        assert!(state.is_finished());
+    }
+
+    #[test]
+    fn generated_sums_every_worker() {
+        let state = SearchState::new(vec![1], 4);
+        for worker in 0..4 {
+            state.add_generated(worker, 1000);
+        }
+        state.add_remote(500);
+        assert_eq!(state.generated(), 4500);
+        assert!(!state.is_finished());
+        state.stop();
+        assert!(state.is_finished());
+    }
+
+    #[test]
+    fn retired_patterns_are_never_claimed() {
+        let state = SearchState::new(vec![2, 1], 1);
+        assert_eq!(state.claim(&[0]), Some(0));
+        state.retire(0);
+        assert_eq!(state.claim(&[0, 1]), Some(1));
+        assert_eq!(state.remaining(), vec![0, 0]);
+        assert!(state.all_found());
+    }
+}
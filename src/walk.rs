@@ -0,0 +1,383 @@
+//! Fast key generation by walking along the curve.
+//!
+//! Instead of deriving every keypair from a fresh seed, a walk picks one
+//! random scalar `s` and then steps through `s + 1, s + 2, ...`, getting each
+//! public key from the previous one with a single point addition. Encoding a
+//! point needs a field inversion, so a whole batch of points is normalized
+//! with one shared inversion (Montgomery's trick).
+//!
+//! The keys of one walk are related: whoever holds one of them can find
+//! every other key the same walk produced by stepping from their own until
+//! the public key matches, which takes at most as many steps as the walk
+//! made. Keys found together are only as separate as their least careful
+//! holder, so search with fresh keypairs when they go to different owners.
+//!
+//! The keys found this way are raw scalars, not seeds: they can't be turned
+//! into a Solana keypair file, but they can sign. See
+//! [`ExpandedKeypair`](crate::expanded_key::ExpandedKeypair).
+
+use curve25519_dalek::{
+    constants::ED25519_BASEPOINT_COMPRESSED, traits::Identity, EdwardsPoint, Scalar,
+};
+use fiat_crypto::curve25519_64::{
+    fiat_25519_add, fiat_25519_carry, fiat_25519_carry_mul, fiat_25519_carry_square,
+    fiat_25519_from_bytes, fiat_25519_loose_field_element, fiat_25519_opp, fiat_25519_relax,
+    fiat_25519_sub, fiat_25519_tight_field_element, fiat_25519_to_bytes,
+};
+use rand::{rngs::OsRng, RngCore};
+use zeroize::{Zeroize, Zeroizing};
+
+/// `p - 2`, little-endian; raising to it inverts.
+const P_MINUS_2: [u8; 32] = exponent(0xeb, 0x7f);
+/// `(p - 5) / 8`, little-endian; used for square roots.
+const P_MINUS_5_OVER_8: [u8; 32] = exponent(0xfd, 0x0f);
+/// `(p - 1) / 4`, little-endian; `2` raised to it is a square root of `-1`.
+const P_MINUS_1_OVER_4: [u8; 32] = exponent(0xfb, 0x1f);
+
+/// An exponent of the form `0xhh_ff..ff_ll`.
+const fn exponent(low: u8, high: u8) -> [u8; 32] {
+    let mut bytes = [0xff; 32];
+    bytes[0] = low;
+    bytes[31] = high;
+    bytes
+}
+
+/// A run of public keys for consecutive scalars. The secret scalar is wiped
+/// when the walk is dropped.
+pub struct ScalarWalk {
+    /// Scalar of the first key in the current batch.
+    batch_scalar: Scalar,
+    /// The point after the last one in the current batch.
+    next_point: ExtendedPoint,
+    base: NielsPoint,
+    points: Vec<ExtendedPoint>,
+    keys: Vec<[u8; 32]>,
+}
+
+impl ScalarWalk {
+    /// Starts a walk at a random scalar, producing `batch_size` keys at a
+    /// time.
+    pub fn new(batch_size: usize) -> Self {
+        let mut seed = Zeroizing::new([0u8; 64]);
+        OsRng.fill_bytes(&mut *seed);
+        ScalarWalk::from_scalar(Scalar::from_bytes_mod_order_wide(&seed), batch_size)
+    }
+
+    /// Starts a walk at `scalar`.
+    pub fn from_scalar(scalar: Scalar, batch_size: usize) -> Self {
+        ScalarWalk::offset_from(&EdwardsPoint::identity(), scalar, batch_size)
+    }
+
+    /// Starts a walk at a random offset from `partial`: the keys are
+    /// `partial + (offset + i) * B`, and [`scalar`](Self::scalar) gives
+    /// the offsets rather than secret keys. See [`crate::split_key`].
+    pub fn from_partial(partial: &EdwardsPoint, batch_size: usize) -> Self {
+        let mut seed = Zeroizing::new([0u8; 64]);
+        OsRng.fill_bytes(&mut *seed);
+        ScalarWalk::offset_from(
+            partial,
+            Scalar::from_bytes_mod_order_wide(&seed),
+            batch_size,
+        )
+    }
+
+    fn offset_from(partial: &EdwardsPoint, scalar: Scalar, batch_size: usize) -> Self {
+        let start = (partial + EdwardsPoint::mul_base(&scalar))
+            .compress()
+            .to_bytes();
+        let (x, y) = decompress(&start).expect("points of the curve decompress");
+        let (bx, by) = decompress(&ED25519_BASEPOINT_COMPRESSED.to_bytes())
+            .expect("the base point is on the curve");
+        ScalarWalk {
+            batch_scalar: scalar,
+            next_point: ExtendedPoint::from_affine(x, y),
+            base: NielsPoint::from_affine(bx, by),
+            points: Vec::with_capacity(batch_size),
+            keys: vec![[0u8; 32]; batch_size],
+        }
+    }
+
+    /// Steps through the next batch of keys.
+    pub fn next_batch(&mut self) {
+        if !self.points.is_empty() {
+            self.batch_scalar += Scalar::from(self.points.len() as u64);
+        }
+        self.points.clear();
+        for _ in 0..self.keys.len() {
+            let next = self.next_point.add(&self.base);
+            self.points.push(self.next_point);
+            self.next_point = next;
+        }
+        compress_batch(&self.points, &mut self.keys);
+    }
+
+    /// Public keys of the last batch. Key `i` belongs to
+    /// [`scalar(i)`](Self::scalar).
+    pub fn keys(&self) -> &[[u8; 32]] {
+        &self.keys
+    }
+
+    /// Secret scalar of key `index` in the last batch.
+    pub fn scalar(&self, index: usize) -> Scalar {
+        self.batch_scalar + Scalar::from(index as u64)
+    }
+}
+
+impl Drop for ScalarWalk {
+    fn drop(&mut self) {
+        self.batch_scalar.zeroize();
+    }
+}
+
+/// Normalizes every point with one inversion and writes its compressed
+/// encoding to `out`.
+fn compress_batch(points: &[ExtendedPoint], out: &mut [[u8; 32]]) {
+    let mut products = Vec::with_capacity(points.len());
+    let mut acc = FieldElement::ONE;
+    for point in points {
+        products.push(acc);
+        acc = acc.mul(&point.z);
+    }
+    let mut inverse = acc.invert();
+    for (i, point) in points.iter().enumerate().rev() {
+        let z_inv = inverse.mul(&products[i]);
+        inverse = inverse.mul(&point.z);
+
+        let x = point.x.mul(&z_inv);
+        let mut bytes = point.y.mul(&z_inv).to_bytes();
+        bytes[31] |= x.is_negative() << 7;
+        out[i] = bytes;
+    }
+}
+
+/// Decodes a compressed point to affine `(x, y)`, or `None` if it isn't
+/// on the curve.
+fn decompress(bytes: &[u8; 32]) -> Option<(FieldElement, FieldElement)> {
+    let mut y_bytes = *bytes;
+    y_bytes[31] &= 0x7f;
+    let y = FieldElement::from_bytes(&y_bytes);
+    let yy = y.square();
+    let u = yy.sub(&FieldElement::ONE);
+    let v = edwards_d().mul(&yy).add(&FieldElement::ONE);
+
+    // x = u v^3 (u v^7)^((p - 5) / 8), possibly times sqrt(-1).
+    let v3 = v.square().mul(&v);
+    let v7 = v3.square().mul(&v);
+    let mut x = u.mul(&v3).mul(&u.mul(&v7).pow(&P_MINUS_5_OVER_8));
+    let vxx = v.mul(&x.square());
+    if vxx != u {
+        if vxx != u.neg() {
+            return None;
+        }
+        x = x.mul(&sqrt_minus_one());
+    }
+    if x.is_negative() != bytes[31] >> 7 {
+        x = x.neg();
+    }
+    Some((x, y))
+}
+
+/// The curve constant `d = -121665 / 121666`.
+fn edwards_d() -> FieldElement {
+    FieldElement::from_u64(121665)
+        .neg()
+        .mul(&FieldElement::from_u64(121666).invert())
+}
+
+fn sqrt_minus_one() -> FieldElement {
+    FieldElement::from_u64(2).pow(&P_MINUS_1_OVER_4)
+}
+
+/// A point in extended twisted Edwards coordinates, `x = X/Z`, `y = Y/Z`,
+/// `xy = T/Z`.
+#[derive(Clone, Copy)]
+struct ExtendedPoint {
+    x: FieldElement,
+    y: FieldElement,
+    z: FieldElement,
+    t: FieldElement,
+}
+
+/// An affine point prepared for mixed addition.
+#[derive(Clone, Copy)]
+struct NielsPoint {
+    y_plus_x: FieldElement,
+    y_minus_x: FieldElement,
+    xy2d: FieldElement,
+}
+
+impl ExtendedPoint {
+    fn from_affine(x: FieldElement, y: FieldElement) -> Self {
+        ExtendedPoint {
+            x,
+            y,
+            z: FieldElement::ONE,
+            t: x.mul(&y),
+        }
+    }
+
+    /// Adds an affine point (Hisil–Wong–Carter–Dawson, `a = -1`).
+    fn add(&self, other: &NielsPoint) -> ExtendedPoint {
+        let pp = self.y.add(&self.x).mul(&other.y_plus_x);
+        let mm = self.y.sub(&self.x).mul(&other.y_minus_x);
+        let txy2d = self.t.mul(&other.xy2d);
+        let z2 = self.z.add(&self.z);
+
+        let x = pp.sub(&mm);
+        let y = pp.add(&mm);
+        let z = z2.add(&txy2d);
+        let t = z2.sub(&txy2d);
+        ExtendedPoint {
+            x: x.mul(&t),
+            y: y.mul(&z),
+            z: z.mul(&t),
+            t: x.mul(&y),
+        }
+    }
+}
+
+impl NielsPoint {
+    fn from_affine(x: FieldElement, y: FieldElement) -> Self {
+        let d2 = edwards_d().add(&edwards_d());
+        NielsPoint {
+            y_plus_x: y.add(&x),
+            y_minus_x: y.sub(&x),
+            xy2d: x.mul(&y).mul(&d2),
+        }
+    }
+}
+
+/// An element of GF(2^255 - 19), backed by fiat-crypto's verified
+/// arithmetic.
+#[derive(Clone, Copy)]
+struct FieldElement(fiat_25519_tight_field_element);
+
+impl FieldElement {
+    const ONE: FieldElement = FieldElement(fiat_25519_tight_field_element([1, 0, 0, 0, 0]));
+
+    fn from_u64(n: u64) -> Self {
+        let mut bytes = [0u8; 32];
+        bytes[..8].copy_from_slice(&n.to_le_bytes());
+        FieldElement::from_bytes(&bytes)
+    }
+
+    /// Reads a little-endian value below `2^255`.
+    fn from_bytes(bytes: &[u8; 32]) -> Self {
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_from_bytes(&mut out, bytes);
+        FieldElement(out)
+    }
+
+    /// The canonical little-endian encoding.
+    fn to_bytes(self) -> [u8; 32] {
+        let mut out = [0u8; 32];
+        fiat_25519_to_bytes(&mut out, &self.0);
+        out
+    }
+
+    fn is_negative(self) -> u8 {
+        self.to_bytes()[0] & 1
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        let mut loose = fiat_25519_loose_field_element([0; 5]);
+        fiat_25519_add(&mut loose, &self.0, &other.0);
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_carry(&mut out, &loose);
+        FieldElement(out)
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        let mut loose = fiat_25519_loose_field_element([0; 5]);
+        fiat_25519_sub(&mut loose, &self.0, &other.0);
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_carry(&mut out, &loose);
+        FieldElement(out)
+    }
+
+    fn neg(&self) -> Self {
+        let mut loose = fiat_25519_loose_field_element([0; 5]);
+        fiat_25519_opp(&mut loose, &self.0);
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_carry(&mut out, &loose);
+        FieldElement(out)
+    }
+
+    fn mul(&self, other: &Self) -> Self {
+        let (mut a, mut b) = (
+            fiat_25519_loose_field_element([0; 5]),
+            fiat_25519_loose_field_element([0; 5]),
+        );
+        fiat_25519_relax(&mut a, &self.0);
+        fiat_25519_relax(&mut b, &other.0);
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_carry_mul(&mut out, &a, &b);
+        FieldElement(out)
+    }
+
+    fn square(&self) -> Self {
+        let mut a = fiat_25519_loose_field_element([0; 5]);
+        fiat_25519_relax(&mut a, &self.0);
+        let mut out = fiat_25519_tight_field_element([0; 5]);
+        fiat_25519_carry_square(&mut out, &a);
+        FieldElement(out)
+    }
+
+    /// Raises to a public little-endian exponent.
+    fn pow(&self, exponent: &[u8; 32]) -> Self {
+        let mut result = FieldElement::ONE;
+        for byte in exponent.iter().rev() {
+            for bit in (0..8).rev() {
+                result = result.square();
+                if (byte >> bit) & 1 == 1 {
+                    result = result.mul(self);
+                }
+            }
+        }
+        result
+    }
+
+    fn invert(&self) -> Self {
+        self.pow(&P_MINUS_2)
+    }
+}
+
+impl PartialEq for FieldElement {
+    fn eq(&self, other: &Self) -> bool {
+        self.to_bytes() == other.to_bytes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walk_matches_scalar_multiplication() {
+        let start = Scalar::from_bytes_mod_order([7u8; 32]);
+        let mut walk = ScalarWalk::from_scalar(start, 16);
+        for _ in 0..3 {
+            walk.next_batch();
+            for (i, key) in walk.keys().iter().enumerate() {
+                let expected = EdwardsPoint::mul_base(&walk.scalar(i)).compress();
+                assert_eq!(key, expected.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn partial_walk_adds_the_offset_to_the_partial_point() {
+        let partial = EdwardsPoint::mul_base(&Scalar::from(99u64));
+        let mut walk = ScalarWalk::from_partial(&partial, 8);
+        walk.next_batch();
+        for (i, key) in walk.keys().iter().enumerate() {
+            let expected = (partial + EdwardsPoint::mul_base(&walk.scalar(i))).compress();
+            assert_eq!(key, expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn sqrt_minus_one_squares_to_minus_one() {
+        assert!(sqrt_minus_one().square() == FieldElement::ONE.neg());
+    }
+}
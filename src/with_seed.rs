@@ -0,0 +1,185 @@
+//! Vanity addresses derived with `Pubkey::create_with_seed`.
+//!
+//! An account created by `create_account_with_seed` lives at the hash of a
+//! base pubkey, a seed string of at most 32 bytes and the owner program.
+//! The base key signs for the account, so an existing signer can get a
+//! vanity account address just by picking the seed. The search fills the
+//! `?`s of a seed template with random characters from an alphabet; a
+//! match is the base, seed and owner to create it with, nothing secret.
+
+use rand::Rng;
+use solana_sdk::{
+    hash::hashv,
+    pubkey::{Pubkey, MAX_SEED_LEN},
+};
+
+/// Marks a character of the seed template that the search fills in.
+pub const PLACEHOLDER: char = '?';
+
+/// Twelve random characters.
+pub const DEFAULT_TEMPLATE: &str = "????????????";
+
+pub const DEFAULT_ALPHABET: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Program addresses end with this marker before hashing, and the runtime
+/// refuses it at the end of an owner so the two kinds of address can't
+/// collide.
+const PDA_MARKER: &[u8] = b"ProgramDerivedAddress";
+
+/// The base, owner and seed template of a `create_with_seed` search.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WithSeedSettings {
+    pub base: Pubkey,
+    pub owner: Pubkey,
+    /// The seed, with [`PLACEHOLDER`] for each character to search.
+    pub template: String,
+    /// Characters the placeholders are filled with.
+    pub alphabet: String,
+}
+
+impl WithSeedSettings {
+    /// Checks that the seed fits, has something to search and that the
+    /// owner can own accounts created with a seed.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.template.len() > MAX_SEED_LEN {
+            return Err(format!(
+                "seed template `{}` is longer than {} bytes",
+                self.template, MAX_SEED_LEN
+            ));
+        }
+        if !self.template.contains(PLACEHOLDER) {
+            return Err(format!(
+                "seed template `{}` has no `{}` to search",
+                self.template, PLACEHOLDER
+            ));
+        }
+        if self.alphabet.is_empty() {
+            return Err("the seed alphabet is empty".to_string());
+        }
+        if let Some(c) = self.alphabet.chars().find(|c| !c.is_ascii_graphic()) {
+            return Err(format!(
+                "the seed alphabet can only hold printable ASCII, got {:?}",
+                c
+            ));
+        }
+        if let Some(c) = self
+            .alphabet
+            .char_indices()
+            .find(|&(i, c)| self.alphabet[..i].contains(c))
+            .map(|(_, c)| c)
+        {
+            return Err(format!("`{}` appears twice in the seed alphabet", c));
+        }
+        if self.owner.as_ref().ends_with(PDA_MARKER) {
+            return Err(format!(
+                "`{}` cannot own accounts created with a seed",
+                self.owner
+            ));
+        }
+        Ok(())
+    }
+
+    /// A buffer of candidate seeds for one worker.
+    pub fn seeds(&self) -> SeedBuffer {
+        SeedBuffer {
+            seed: self.template.as_bytes().to_vec(),
+            placeholders: self
+                .template
+                .bytes()
+                .enumerate()
+                .filter(|&(_, byte)| byte == PLACEHOLDER as u8)
+                .map(|(i, _)| i)
+                .collect(),
+            alphabet: self.alphabet.as_bytes().to_vec(),
+        }
+    }
+
+    /// The address `seed` gives, as `Pubkey::create_with_seed` would
+    /// compute it once `validate` has checked the owner.
+    pub fn address(&self, seed: &[u8]) -> Pubkey {
+        Pubkey::new_from_array(hashv(&[self.base.as_ref(), seed, self.owner.as_ref()]).to_bytes())
+    }
+}
+
+/// The seed template with its placeholders filled in, reused from one
+/// candidate to the next.
+pub struct SeedBuffer {
+    seed: Vec<u8>,
+    placeholders: Vec<usize>,
+    alphabet: Vec<u8>,
+}
+
+impl SeedBuffer {
+    /// Fills the placeholders with fresh random characters.
+    pub fn next(&mut self, rng: &mut impl Rng) -> &[u8] {
+        for &i in &self.placeholders {
+            self.seed[i] = self.alphabet[rng.gen_range(0..self.alphabet.len())];
+        }
+        &self.seed
+    }
+
+    /// The current seed.
+    pub fn as_str(&self) -> &str {
+        // The template is a string and the alphabet ASCII, so putting
+        // characters of one in place of ASCII placeholders keeps it UTF-8.
+        std::str::from_utf8(&self.seed).expect("seeds are UTF-8")
+    }
+}
+
+/// How to create a matching account with `create_account_with_seed`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountSeed {
+    pub base: Pubkey,
+    pub seed: String,
+    pub owner: Pubkey,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(template: &str, alphabet: &str) -> WithSeedSettings {
+        WithSeedSettings {
+            base: Pubkey::new_unique(),
+            owner: solana_sdk::system_program::id(),
+            template: template.to_string(),
+            alphabet: alphabet.to_string(),
+        }
+    }
+
+    #[test]
+    fn matches_create_with_seed() {
+        let settings = settings("vault-????", "xyz");
+        settings.validate().unwrap();
+        let mut seeds = settings.seeds();
+        let mut rng = rand::thread_rng();
+        for _ in 0..10 {
+            let address = settings.address(seeds.next(&mut rng));
+            let seed = seeds.as_str();
+            assert!(seed.starts_with("vault-"));
+            assert!(seed[6..].chars().all(|c| "xyz".contains(c)), "{}", seed);
+            assert_eq!(
+                Ok(address),
+                Pubkey::create_with_seed(&settings.base, seed, &settings.owner)
+            );
+        }
+    }
+
+    #[test]
+    fn settings_are_checked() {
+        assert!(settings(DEFAULT_TEMPLATE, DEFAULT_ALPHABET)
+            .validate()
+            .is_ok());
+        assert!(settings(&"?".repeat(33), "ab").validate().is_err());
+        assert!(settings("vault", "ab").validate().is_err());
+        assert!(settings("??", "").validate().is_err());
+        assert!(settings("??", "a b").validate().is_err());
+        assert!(settings("??", "aba").validate().is_err());
+
+        let mut owner = [7u8; 32];
+        owner[32 - PDA_MARKER.len()..].copy_from_slice(PDA_MARKER);
+        let mut pda_owner = settings("??", "ab");
+        pda_owner.owner = Pubkey::new_from_array(owner);
+        assert!(pda_owner.validate().is_err());
+    }
+}
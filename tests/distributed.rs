@@ -0,0 +1,92 @@
+//! A distributed search across separate processes on localhost.
+
+use serde_json::Value;
+use solana_sdk::{signature::Signer, signer::keypair::Keypair};
+use std::{
+    fs,
+    io::{self, BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    thread,
+};
+
+const VANADDY: &str = env!("CARGO_BIN_EXE_vanaddy");
+
+#[test]
+fn worker_processes_find_exactly_the_target_count() {
+    let path =
+        std::env::temp_dir().join(format!("vanaddy-distributed-{}.jsonl", std::process::id()));
+    let _ = fs::remove_file(&path);
+    let mut coordinator = Command::new(VANADDY)
+        .args([
+            "--listen",
+            "127.0.0.1:0",
+            "-t",
+            "0",
+            "-f",
+            "jsonl",
+            "--jsonl-output",
+        ])
+        .arg(&path)
+        .args(["AB:3", "Ab:2"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut status = BufReader::new(coordinator.stdout.take().unwrap());
+    let address = loop {
+        let mut line = String::new();
+        assert_ne!(
+            status.read_line(&mut line).unwrap(),
+            0,
+            "the coordinator exited"
+        );
+        if let Some(address) = line.trim().strip_prefix("Listening for workers on ") {
+            break address.to_owned();
+        }
+    };
+    // Keep reading the progress line so the coordinator never blocks on it
+    thread::spawn(move || io::copy(&mut status, &mut io::sink()));
+
+    let workers: Vec<Child> = (0..3)
+        .map(|_| {
+            Command::new(VANADDY)
+                .args(["worker", &address, "-t", "1"])
+                .stderr(Stdio::piped())
+                .spawn()
+                .unwrap()
+        })
+        .collect();
+    assert!(coordinator.wait().unwrap().success());
+    for worker in workers {
+        let output = worker.wait_with_output().unwrap();
+        let report = String::from_utf8(output.stderr).unwrap();
+        assert!(output.status.success(), "{}", report);
+        // Every worker joined in time to search
+        assert!(!report.contains("; 0 wallets generated"), "{}", report);
+    }
+
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let records: Vec<Value> = contents
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    for (pattern, count) in [("AB", 3), ("Ab", 2)] {
+        assert_eq!(
+            records
+                .iter()
+                .filter(|record| record["pattern"] == pattern)
+                .count(),
+            count,
+            "{}",
+            contents
+        );
+    }
+    assert_eq!(records.len(), 5);
+    for record in &records {
+        let pubkey = record["pubkey"].as_str().unwrap();
+        assert!(pubkey.starts_with(record["pattern"].as_str().unwrap()));
+        let keypair = Keypair::from_base58_string(record["secret"].as_str().unwrap());
+        assert_eq!(keypair.pubkey().to_string(), pubkey);
+    }
+}